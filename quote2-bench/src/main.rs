@@ -0,0 +1,243 @@
+//! Benchmarks `quote2::quote!` against `quote::quote!` on the derive
+//! templates in [`templates`], and prints the results as JSON.
+//!
+//! ```text
+//! cargo run --release -p quote2-bench -- [--fields N] [--time-ms MS] [--expanded]
+//! ```
+//!
+//! For every template and implementation it reports:
+//!
+//! - `ns_per_iter`: the mean time to build the token stream.
+//! - `allocs` and `alloc_bytes`: the heap allocations of one run, counted by
+//!   the global allocator.
+//! - `tokens`: the size of the output, counting every token tree.
+//! - `expanded_bytes`: only with `--expanded`, the size of the template
+//!   function after macro expansion, a proxy for the compile time it costs.
+//!   This runs `cargo rustc -- -Zunpretty=expanded` on this crate, with
+//!   `RUSTC_BOOTSTRAP=1` so that it also works on a stable toolchain.
+mod templates;
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote2::testing::assert_tokens_eq;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::process::{exit, Command};
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use std::time::{Duration, Instant};
+use std::{env, fmt::Write};
+use templates::{Input, TEMPLATES};
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static ALLOC_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts allocations and reallocations, then defers to [`System`].
+struct Counting;
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Relaxed);
+        ALLOC_BYTES.fetch_add(layout.size(), Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCS.fetch_add(1, Relaxed);
+        ALLOC_BYTES.fetch_add(new_size, Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct Options {
+    fields: usize,
+    time: Duration,
+    expanded: bool,
+}
+
+fn options() -> Options {
+    let mut options = Options {
+        fields: 16,
+        time: Duration::from_millis(300),
+        expanded: false,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| -> u64 {
+            match args.next().and_then(|v| v.parse().ok()) {
+                Some(v) => v,
+                None => usage(&format!("`{name}` expects a number")),
+            }
+        };
+        match arg.as_str() {
+            "--fields" => options.fields = value("--fields") as usize,
+            "--time-ms" => options.time = Duration::from_millis(value("--time-ms")),
+            "--expanded" => options.expanded = true,
+            _ => usage(&format!("unknown argument `{arg}`")),
+        }
+    }
+    options
+}
+
+fn usage(error: &str) -> ! {
+    eprintln!("error: {error}");
+    eprintln!("usage: quote2-bench [--fields N] [--time-ms MS] [--expanded]");
+    exit(2)
+}
+
+fn main() {
+    let options = options();
+    let input = Input::new(options.fields);
+    let expanded = options.expanded.then(|| {
+        expanded_source().unwrap_or_else(|err| {
+            eprintln!("error: failed to expand the templates: {err}");
+            exit(1)
+        })
+    });
+
+    let mut results = Vec::new();
+    for template in &TEMPLATES {
+        let output = (template.quote2)(&input);
+        // Same tokens, up to spacing.
+        assert_tokens_eq!(output, (template.quote)(&input));
+        let tokens = count_tokens(output);
+        for (imp, f) in [("quote2", template.quote2), ("quote", template.quote)] {
+            let (ns_per_iter, iters) = time(f, &input, options.time);
+            let (allocs, alloc_bytes) = allocations(f, &input);
+            let expanded_bytes = match &expanded {
+                Some(source) => {
+                    let name = format!("{}_{imp}", template.name);
+                    match function_size(source, &name) {
+                        Some(size) => size.to_string(),
+                        None => {
+                            eprintln!("error: `fn {name}` not found in the expanded source");
+                            exit(1)
+                        }
+                    }
+                }
+                None => "null".into(),
+            };
+            results.push(format!(
+                "{{\"template\": \"{}\", \"impl\": \"{imp}\", \"ns_per_iter\": {ns_per_iter:.1}, \
+                 \"iters\": {iters}, \"allocs\": {allocs}, \"alloc_bytes\": {alloc_bytes}, \
+                 \"tokens\": {tokens}, \"expanded_bytes\": {expanded_bytes}}}",
+                template.name
+            ));
+        }
+    }
+
+    let mut json = format!("{{\n  \"fields\": {},\n  \"results\": [\n", options.fields);
+    for (i, result) in results.iter().enumerate() {
+        let comma = if i + 1 < results.len() { "," } else { "" };
+        writeln!(json, "    {result}{comma}").unwrap();
+    }
+    json.push_str("  ]\n}");
+    println!("{json}");
+}
+
+/// Runs `f` for about `budget`, returns the mean time per run in nanoseconds
+/// and the number of runs.
+fn time(f: fn(&Input) -> TokenStream, input: &Input, budget: Duration) -> (f64, u64) {
+    const BATCH: u64 = 16;
+    for _ in 0..BATCH {
+        black_box(f(black_box(input)));
+    }
+    let mut iters = 0;
+    let start = Instant::now();
+    while start.elapsed() < budget {
+        for _ in 0..BATCH {
+            black_box(f(black_box(input)));
+        }
+        iters += BATCH;
+    }
+    let ns = start.elapsed().as_nanos() as f64 / iters as f64;
+    (ns, iters)
+}
+
+/// The allocations of a single run of `f`, dropping the output included.
+fn allocations(f: fn(&Input) -> TokenStream, input: &Input) -> (usize, usize) {
+    let (allocs, bytes) = (ALLOCS.load(Relaxed), ALLOC_BYTES.load(Relaxed));
+    drop(black_box(f(black_box(input))));
+    (
+        ALLOCS.load(Relaxed) - allocs,
+        ALLOC_BYTES.load(Relaxed) - bytes,
+    )
+}
+
+fn count_tokens(stream: TokenStream) -> usize {
+    stream
+        .into_iter()
+        .map(|tt| match tt {
+            TokenTree::Group(g) => 1 + count_tokens(g.stream()),
+            _ => 1,
+        })
+        .sum()
+}
+
+/// This crate after macro expansion.
+fn expanded_source() -> Result<String, String> {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo)
+        .args(["rustc", "--quiet", "--bin", "quote2-bench"])
+        .arg("--manifest-path")
+        .arg(format!("{dir}/Cargo.toml"))
+        // A separate target directory, the running binary holds the other one.
+        .arg("--target-dir")
+        .arg(format!("{dir}/../target/expand"))
+        .args(["--", "-Zunpretty=expanded"])
+        .env("RUSTC_BOOTSTRAP", "1")
+        .output()
+        .map_err(|err| err.to_string())?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+    }
+    String::from_utf8(output.stdout).map_err(|err| err.to_string())
+}
+
+/// The size of the body of `fn name` in `source`, with runs of whitespace
+/// counted as one byte.
+fn function_size(source: &str, name: &str) -> Option<usize> {
+    let start = source.find(&format!("fn {name}("))?;
+    let body = &source[start..];
+    let open = body.find('{')?;
+    let chars: Vec<char> = body[open..].chars().collect();
+    let (mut depth, mut i) = (0, 0);
+    let mut end = None;
+    while i < chars.len() {
+        match chars[i] {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    end = Some(i);
+                    break;
+                }
+            }
+            '"' => {
+                i += 1;
+                while chars[i] != '"' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+            }
+            // A char literal like `'{'` or `'\''`, not a lifetime.
+            '\'' if chars.get(i + 1) == Some(&'\\') => {
+                i += 3;
+                while chars[i] != '\'' {
+                    i += 1;
+                }
+            }
+            '\'' if chars.get(i + 2) == Some(&'\'') => i += 2,
+            _ => {}
+        }
+        i += 1;
+    }
+    let body: String = chars[..=end?].iter().collect();
+    Some(body.split_whitespace().map(|word| word.len() + 1).sum())
+}
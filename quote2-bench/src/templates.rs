@@ -0,0 +1,242 @@
+//! Representative derive templates, written once with `quote2::quote!` and
+//! once with `quote::quote!`. Both versions of a template produce the same
+//! tokens, `main` checks it before measuring.
+//!
+//! Every function is looked up by name in the expanded source for the
+//! `expanded_bytes` column, so the bodies should stay self contained.
+use proc_macro2::{Ident, Span, TokenStream};
+use quote2::format_ident;
+
+pub struct Input {
+    pub name: Ident,
+    pub fields: Vec<(Ident, TokenStream)>,
+    pub variants: Vec<Ident>,
+}
+
+impl Input {
+    /// A struct with `n` fields and an enum with `n` unit variants.
+    pub fn new(n: usize) -> Self {
+        let tys = ["u8", "String", "Option<u64>", "Vec<(u32, bool)>"];
+        Self {
+            name: Ident::new("Sample", Span::call_site()),
+            fields: (0..n)
+                .map(|i| {
+                    let ty = tys[i % tys.len()].parse().unwrap();
+                    (format_ident!("field_{i}"), ty)
+                })
+                .collect(),
+            variants: (0..n).map(|i| format_ident!("Variant{i}")).collect(),
+        }
+    }
+}
+
+pub struct Template {
+    pub name: &'static str,
+    pub quote2: fn(&Input) -> TokenStream,
+    pub quote: fn(&Input) -> TokenStream,
+}
+
+pub const TEMPLATES: [Template; 4] = [
+    Template {
+        name: "debug",
+        quote2: debug_quote2,
+        quote: debug_quote,
+    },
+    Template {
+        name: "builder",
+        quote2: builder_quote2,
+        quote: builder_quote,
+    },
+    Template {
+        name: "display_enum",
+        quote2: display_enum_quote2,
+        quote: display_enum_quote,
+    },
+    Template {
+        name: "fixed_impl",
+        quote2: fixed_impl_quote2,
+        quote: fixed_impl_quote,
+    },
+];
+
+pub fn debug_quote2(input: &Input) -> TokenStream {
+    let name = &input.name;
+    let name_str = name.to_string();
+    let mut t = TokenStream::new();
+    quote2::quote!(t, {
+        impl ::core::fmt::Debug for #name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.debug_struct(#name_str)
+                    #for (field, _) in &input.fields {
+                        .field(#{field.to_string()}, &self.#field)
+                    }
+                    .finish()
+            }
+        }
+    });
+    t
+}
+
+pub fn debug_quote(input: &Input) -> TokenStream {
+    let name = &input.name;
+    let name_str = name.to_string();
+    let fields = input.fields.iter().map(|(field, _)| field);
+    let strs = input.fields.iter().map(|(field, _)| field.to_string());
+    quote::quote! {
+        impl ::core::fmt::Debug for #name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.debug_struct(#name_str)
+                    #(.field(#strs, &self.#fields))*
+                    .finish()
+            }
+        }
+    }
+}
+
+pub fn builder_quote2(input: &Input) -> TokenStream {
+    let name = &input.name;
+    let builder = format_ident!("{name}Builder");
+    let mut t = TokenStream::new();
+    quote2::quote!(t, {
+        #[derive(Default)]
+        pub struct #builder {
+            #for (field, ty) in &input.fields {
+                #field: ::core::option::Option<#ty>,
+            }
+        }
+        impl #builder {
+            #for (field, ty) in &input.fields {
+                pub fn #field(mut self, value: #ty) -> Self {
+                    self.#field = ::core::option::Option::Some(value);
+                    self
+                }
+            }
+            pub fn build(self) -> ::core::result::Result<#name, &'static str> {
+                ::core::result::Result::Ok(#name {
+                    #for (field, _) in &input.fields {
+                        #field: self.#field.ok_or(#{field.to_string()})?,
+                    }
+                })
+            }
+        }
+    });
+    t
+}
+
+pub fn builder_quote(input: &Input) -> TokenStream {
+    let name = &input.name;
+    let builder = format_ident!("{name}Builder");
+    let fields: Vec<_> = input.fields.iter().map(|(field, _)| field).collect();
+    let tys: Vec<_> = input.fields.iter().map(|(_, ty)| ty).collect();
+    let strs = fields.iter().map(|field| field.to_string());
+    quote::quote! {
+        #[derive(Default)]
+        pub struct #builder {
+            #(#fields: ::core::option::Option<#tys>,)*
+        }
+        impl #builder {
+            #(
+                pub fn #fields(mut self, value: #tys) -> Self {
+                    self.#fields = ::core::option::Option::Some(value);
+                    self
+                }
+            )*
+            pub fn build(self) -> ::core::result::Result<#name, &'static str> {
+                ::core::result::Result::Ok(#name {
+                    #(#fields: self.#fields.ok_or(#strs)?,)*
+                })
+            }
+        }
+    }
+}
+
+pub fn display_enum_quote2(input: &Input) -> TokenStream {
+    let name = &input.name;
+    let mut t = TokenStream::new();
+    quote2::quote!(t, {
+        impl ::core::fmt::Display for #name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(match self {
+                    #for variant in &input.variants {
+                        Self::#variant => #{variant.to_string()},
+                    }
+                })
+            }
+        }
+    });
+    t
+}
+
+pub fn display_enum_quote(input: &Input) -> TokenStream {
+    let name = &input.name;
+    let variants = &input.variants;
+    let strs = variants.iter().map(|variant| variant.to_string());
+    quote::quote! {
+        impl ::core::fmt::Display for #name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(match self {
+                    #(Self::#variants => #strs,)*
+                })
+            }
+        }
+    }
+}
+
+/// Fixed glue around a single interpolation, `quote2` writes it as one string.
+pub fn fixed_impl_quote2(input: &Input) -> TokenStream {
+    let name = &input.name;
+    let mut t = TokenStream::new();
+    quote2::quote!(t, {
+        impl #name {
+            #[inline]
+            pub fn checked_len(items: &[u8], limit: usize) -> ::core::result::Result<usize, &'static str> {
+                let len = items.len();
+                if len > limit {
+                    return ::core::result::Result::Err("too long");
+                }
+                let mut sum = 0usize;
+                for (i, item) in items.iter().enumerate() {
+                    sum = sum.wrapping_add((*item as usize) << (i % 8));
+                }
+                ::core::result::Result::Ok(sum ^ len)
+            }
+            #[inline]
+            pub fn describe(f: &mut ::core::fmt::Formatter<'_>, len: usize) -> ::core::fmt::Result {
+                match len {
+                    0 => f.write_str("empty"),
+                    1..=9 => ::core::write!(f, "{} items", len),
+                    _ => f.write_str("many items"),
+                }
+            }
+        }
+    });
+    t
+}
+
+pub fn fixed_impl_quote(input: &Input) -> TokenStream {
+    let name = &input.name;
+    quote::quote! {
+        impl #name {
+            #[inline]
+            pub fn checked_len(items: &[u8], limit: usize) -> ::core::result::Result<usize, &'static str> {
+                let len = items.len();
+                if len > limit {
+                    return ::core::result::Result::Err("too long");
+                }
+                let mut sum = 0usize;
+                for (i, item) in items.iter().enumerate() {
+                    sum = sum.wrapping_add((*item as usize) << (i % 8));
+                }
+                ::core::result::Result::Ok(sum ^ len)
+            }
+            #[inline]
+            pub fn describe(f: &mut ::core::fmt::Formatter<'_>, len: usize) -> ::core::fmt::Result {
+                match len {
+                    0 => f.write_str("empty"),
+                    1..=9 => ::core::write!(f, "{} items", len),
+                    _ => f.write_str("many items"),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use proc_macro::*;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A malformed invocation, reported as `compile_error!` at the offending token.
+pub struct Error {
+    span: Span,
+    msg: String,
+}
+
+impl Error {
+    pub fn new(span: Span, msg: impl Into<String>) -> Self {
+        Self {
+            span,
+            msg: msg.into(),
+        }
+    }
+
+    /// Spans the error at the token that was found instead of the expected
+    /// one, or at the whole invocation if the input ended early.
+    pub fn unexpected(found: Option<TokenTree>, msg: impl Into<String>) -> Self {
+        let span = found.map_or_else(Span::call_site, |tt| tt.span());
+        Self::new(span, msg)
+    }
+
+    /// `::core::compile_error!("...")`, without a trailing `;` so that it can
+    /// be used in both expression and statement position.
+    pub fn to_compile_error(&self) -> TokenStream {
+        let mut msg = Literal::string(&self.msg);
+        msg.set_span(self.span);
+        let mut args = Group::new(
+            Delimiter::Parenthesis,
+            TokenStream::from(TokenTree::from(msg)),
+        );
+        args.set_span(self.span);
+
+        [
+            TokenTree::from(Punct::new(':', Spacing::Joint)),
+            Punct::new(':', Spacing::Alone).into(),
+            Ident::new("core", self.span).into(),
+            Punct::new(':', Spacing::Joint).into(),
+            Punct::new(':', Spacing::Alone).into(),
+            Ident::new("compile_error", self.span).into(),
+            Punct::new('!', Spacing::Alone).into(),
+            args.into(),
+        ]
+        .into_iter()
+        .map(|mut tt| {
+            tt.set_span(self.span);
+            tt
+        })
+        .collect()
+    }
+}
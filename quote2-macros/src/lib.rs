@@ -1,9 +1,14 @@
+mod error;
+mod lit;
+mod unquote;
+
+use error::{Error, Result};
 use proc_macro::*;
-use std::mem;
+use std::{iter::Peekable, mem};
 
 /// # Example
 /// ```rust
-/// use quote2::{proc_macro2::TokenStream, quote, Quote};
+/// use quote2::{proc_macro2::TokenStream, quote};
 /// let body = quote(|tokens| {
 ///     for i in 0..3 {
 ///         quote!(tokens, {
@@ -28,36 +33,289 @@ use std::mem;
 ///     println!("{}", 2i32);
 /// }
 /// ```
+///
+/// ## Expressions
+///
+/// `#{expr}` interpolates the value of an arbitrary expression. Like `#ident`,
+/// the value is only borrowed (`tokens.add_tokens(&(expr))`), so field
+/// accesses such as `#{self.name}` don't move out of their owner. Inside a
+/// repetition, the expression sees the current item of each repeated variable.
+///
+/// ```rust
+/// use quote2::{format_ident, proc_macro2::{Ident, TokenStream}, quote};
+/// struct Field {
+///     name: Ident,
+///     ty: Ident,
+/// }
+/// let field = Field {
+///     name: format_ident!("id"),
+///     ty: format_ident!("u64"),
+/// };
+/// let mut tokens = TokenStream::new();
+/// quote!(tokens, {
+///     let #{field.name}: #{field.ty} = #{field.name.to_string().len()};
+/// });
+/// assert_eq!(tokens.to_string(), "let id : u64 = 2usize ;");
+/// ```
+///
+/// `#move name` moves `name` into the output instead of borrowing it
+/// (`tokens.add_tokens_once(name)`), so one-shot values such as
+/// `quote2::quote_once` closures can't be interpolated twice by accident.
+///
+/// ## Control flow
+///
+/// `#if`, `#else`, `#for` and `#match` are lowered to plain Rust control flow
+/// around the generated code. Their bodies are templates, `#match` arms must
+/// be written as `Pat => { ... }`.
+///
+/// ```rust
+/// use quote2::{proc_macro2::TokenStream, quote};
+/// let is_pub = true;
+/// let kind = Some("u8");
+/// let mut tokens = TokenStream::new();
+/// quote!(tokens, {
+///     #if is_pub { pub } #else { pub(crate) }
+///     fn max() -> usize {
+///         #match kind {
+///             Some("u8") => { 255 }
+///             _ => { 0 }
+///         }
+///     }
+/// });
+/// assert_eq!(tokens.to_string(), "pub fn max () -> usize { 255 }");
+/// ```
+///
+/// ## Escaping
+///
+/// `##` emits a literal `#`, so `##ident` produces `#ident` instead of
+/// interpolating `ident`. This is useful to generate `quote!` templates or
+/// attribute DSLs that use `#name` themselves, `###name` emits `#` followed by
+/// the value of `name`.
+///
+/// ```rust
+/// use quote2::{format_ident, proc_macro2::TokenStream, quote};
+/// let value = format_ident!("field");
+/// let mut tokens = TokenStream::new();
+/// quote!(tokens, {
+///     quote!(tokens, { ##name: ###value });
+/// });
+/// assert_eq!(tokens.to_string(), "quote ! (tokens , { # name : #field }) ;");
+/// ```
+///
+/// ## Span override
+///
+/// `#x @ span` interpolates `x` with all of its tokens set to `span`, and
+/// `(...) @ span` sets the span of a group's delimiters. The span is either a
+/// variable or a parenthesized expression, e.g. `#ty @ (field.span())`.
+/// Use `#{x} @ ...` to emit a literal `@` after an interpolation, such as in
+/// `#{name} @ Some(_)` patterns.
+///
+/// ```rust
+/// use quote2::{proc_macro2::{Span, TokenStream}, quote};
+/// let ty = quote2::quote(|t| {
+///     quote!(t, { Vec<u8> });
+/// });
+/// let span = Span::call_site();
+/// let mut tokens = TokenStream::new();
+/// quote!(tokens, {
+///     let value: #ty @ span = (1, 2) @ (Span::mixed_site());
+/// });
+/// assert_eq!(tokens.to_string(), "let value : Vec < u8 > = (1 , 2) ;");
+/// ```
+///
+/// ## Static chunks
+///
+/// By default every token of the template becomes one `quote2::tt::*` call.
+/// With the `static-chunks` feature of `quote2`, a run of 64 or more tokens
+/// without any interpolation, directive or span override in `quote!` (not
+/// `quote_spanned!` or `quote_mixed!`) is instead written as a single string,
+/// parsed when the template runs:
+///
+/// ```rust ignore
+/// tokens.add_trees(quote2::tt::parse(&tokens, "impl Default for ..."));
+/// ```
+///
+/// This keeps the expansion of large, mostly fixed templates small, which is
+/// what proc macro compile times are made of, at the cost of parsing the
+/// chunk on every run. The `fixed_impl` template of `quote2-bench` expands to
+/// about 8 times less code this way, and runs about 1.7 times slower when
+/// built with the fallback `proc_macro2` implementation. The parsed tokens are
+/// the same, spacing included, and are spanned with `Span::call_site()` like
+/// any other unspanned token.
+///
+/// The feature is off by default, since parsing the chunks on every run can
+/// leave `quote!` slower than `quote`. Turn it on where the size of the
+/// expansion matters more than the runtime:
+///
+/// ```toml
+/// quote2 = { version = "0.10", features = ["static-chunks"] }
+/// ```
+///
+/// ## Target
+///
+/// The target stream can be any place expression, such as `self.out` or
+/// `*tokens`. It is evaluated again by every write, so the template can
+/// still read the rest of `self`, e.g. `#{self.name()}`. It ends at the first
+/// `,` outside of brackets and turbofish arguments, so
+/// `quote!(f::<A, B>().out, {...})` works as is.
+///
+/// ```rust
+/// use quote2::{proc_macro2::TokenStream, quote};
+/// fn write_unit(name: &str, tokens: &mut TokenStream) {
+///     quote!(*tokens, {
+///         struct #{quote2::format_ident!("{name}")};
+///     });
+/// }
+/// let mut tokens = TokenStream::new();
+/// write_unit("Unit", &mut tokens);
+/// assert_eq!(tokens.to_string(), "struct Unit ;");
+/// ```
+///
+/// ## Repetition
+///
+/// `#(...)*` repeats its body for every item of the iterators interpolated
+/// inside it, zipping them together. Tokens between `)` and `*` are used as
+/// separator. Values that are not iterable are repeated as they are.
+///
+/// ```rust
+/// use quote2::{format_ident, proc_macro2::TokenStream, quote};
+/// let fields = [format_ident!("x"), format_ident!("y")];
+/// let tys = ["i32", "f64"].map(|ty| format_ident!("{ty}"));
+/// let mut tokens = TokenStream::new();
+/// quote!(tokens, {
+///     struct Point {
+///         #(#fields: #tys),*
+///     }
+/// });
+/// assert_eq!(tokens.to_string(), "struct Point { x : i32 , y : f64 }");
+/// ```
 #[proc_macro]
 pub fn quote(input: TokenStream) -> TokenStream {
-    let mut input = input.into_iter();
+    expand_quote(input, Cx::default()).unwrap_or_else(|err| err.to_compile_error())
+}
 
-    let var = parse_arg(&mut input, "expected `ident`");
+fn expand_quote(input: TokenStream, cx: Cx) -> Result<TokenStream> {
+    let mut input = input.into_iter();
 
-    let input = match input.next() {
-        Some(TokenTree::Group(g)) => g.stream(),
-        _ => panic!("expected `{{`"),
-    };
+    let target = parse_arg(&mut input, "expected target stream")?;
+    let input = parse_body(&mut input)?;
 
     let mut output = TokenStream::new();
-    expend(input, &mut output, None, var);
-    output
+    let var = target_var(target);
+    expend(input, &mut output, cx, var)?;
+    Ok(scope(output))
 }
 
-fn parse_arg(input: &mut token_stream::IntoIter, msg: &str) -> Ident {
-    let Some(TokenTree::Ident(var)) = input.next() else {
-        panic!("{msg}")
+/// Takes an expression, up to the next top-level `,`.
+///
+/// Commas between the `<...>` of a turbofish are part of the expression:
+/// `f::<A, B>().out`.
+fn parse_arg(input: &mut token_stream::IntoIter, msg: &str) -> Result<TokenStream> {
+    let mut expr = TokenStream::new();
+    let mut last = None;
+    // Open `<` of generic arguments, and the last two puncts.
+    let mut generics = 0;
+    let mut prev: [Option<Punct>; 2] = [None, None];
+    for tt in input.by_ref() {
+        match &tt {
+            TokenTree::Punct(p) if p.as_char() == ',' && generics == 0 => {
+                if expr.is_empty() {
+                    return Err(Error::new(p.span(), msg));
+                }
+                return Ok(expr);
+            }
+            TokenTree::Punct(p) if p.as_char() == '<' => {
+                let path = matches!(&prev, [Some(a), Some(b)] if a.as_char() == ':'
+                    && a.spacing() == Spacing::Joint
+                    && b.as_char() == ':');
+                if path || generics > 0 {
+                    generics += 1;
+                }
+            }
+            // Not the `>` of `->` or `=>`.
+            TokenTree::Punct(p) if p.as_char() == '>' && generics > 0 => {
+                let arrow = matches!(&prev[1], Some(b) if matches!(b.as_char(), '-' | '=')
+                    && b.spacing() == Spacing::Joint);
+                if !arrow {
+                    generics -= 1;
+                }
+            }
+            _ => {}
+        }
+        prev = match &tt {
+            TokenTree::Punct(p) => [prev[1].take(), Some(p.clone())],
+            _ => [None, None],
+        };
+        last = Some(tt.span());
+        expr.extend([tt]);
+    }
+    match last {
+        Some(span) => Err(Error::new(span, "expected `,`")),
+        None => Err(Error::new(Span::call_site(), msg)),
+    }
+}
+
+fn lone_ident(expr: &TokenStream) -> Option<Ident> {
+    let mut tokens = expr.clone().into_iter();
+    match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Ident(ident)), None) => Some(ident),
+        _ => None,
+    }
+}
+
+/// A lone identifier is written to directly, any other expression is
+/// repeated in every call: `(self.out).add_trees(...)`. Borrowing it once up
+/// front would conflict with `#{self.name()}`, which two-phase borrows allow
+/// in the arguments of each call.
+fn target_var(expr: TokenStream) -> TokenTree {
+    match lone_ident(&expr) {
+        Some(var) => var.into(),
+        None => Group::new(Delimiter::Parenthesis, expr).into(),
+    }
+}
+
+/// `let __span: quote2::proc_macro2::Span = expr;`
+fn span_var(o: &mut TokenStream, expr: TokenStream) -> Ident {
+    if let Some(var) = lone_ident(&expr) {
+        return var;
+    }
+    code(o, "let __span: quote2::proc_macro2::Span =");
+    o.extend(expr);
+    code(o, ";");
+    Ident::new("__span", Span::mixed_site())
+}
+
+/// Wraps the output in a block, which keeps the local introduced by
+/// `span_var` and brings the methods of `quote2::Quote` into
+/// scope, so the caller doesn't have to import it.
+fn scope(output: TokenStream) -> TokenStream {
+    let mut block = TokenStream::new();
+    code(&mut block, "use quote2::Quote as _;");
+    block.extend(output);
+    TokenTree::from(Group::new(Delimiter::Brace, block)).into()
+}
+
+fn parse_body(input: &mut token_stream::IntoIter) -> Result<TokenStream> {
+    let body = match input.next() {
+        Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => g.stream(),
+        tt => return Err(Error::unexpected(tt, "expected `{`")),
     };
-    input.next().expect("expected `,`");
-    var
+    match input.next() {
+        Some(tt) => Err(Error::new(tt.span(), "unexpected token after `}`")),
+        None => Ok(body),
+    }
 }
 
+/// Like [`quote!`], but every token of the template is spanned with the given
+/// span. The span argument can be any expression (e.g. `field.span()`), it is
+/// evaluated exactly once.
+///
 /// ## Example
 ///
 /// ```rust
 /// use quote2::{
 ///     proc_macro2::{Span, TokenStream},
-///     quote_spanned, Quote,
+///     quote_spanned,
 /// };
 /// let span = Span::call_site();
 /// let mut tokens = TokenStream::new();
@@ -69,117 +327,963 @@ fn parse_arg(input: &mut token_stream::IntoIter, msg: &str) -> Ident {
 /// ```
 #[proc_macro]
 pub fn quote_spanned(input: TokenStream) -> TokenStream {
+    expand_quote_spanned(input, false).unwrap_or_else(|err| err.to_compile_error())
+}
+
+fn expand_quote_spanned(input: TokenStream, compat: bool) -> Result<TokenStream> {
     let mut input = input.into_iter();
 
-    let span = parse_arg(&mut input, "expected `span`");
-    let var = parse_arg(&mut input, "expected `ident`");
+    let span = parse_arg(&mut input, "expected span")?;
+    let target = parse_arg(&mut input, "expected target stream")?;
+    let input = parse_body(&mut input)?;
 
-    let input = match input.next() {
-        Some(TokenTree::Group(g)) => g.stream(),
-        _ => panic!("expected `{{`"),
+    let mut output = TokenStream::new();
+    let span = span_var(&mut output, span);
+    let var = target_var(target);
+    let cx = Cx {
+        compat,
+        ..Cx::spanned(&span)
     };
+    expend(input, &mut output, cx, var)?;
+    Ok(scope(output))
+}
+
+/// Same as [`quote!`], but every token of the template is spanned with
+/// [`Span::mixed_site()`](proc_macro::Span::mixed_site), so identifiers
+/// introduced by the template (local variables and labels) are hygienic and
+/// cannot collide with the caller's variables.
+///
+/// Interpolated values keep their own spans. `def_site` hygiene is not
+/// available on stable Rust, so it is not supported.
+///
+/// The tokens are built by the `quote2::tt::Site` helpers, which take their
+/// span from a `quote2::tt::SpanSource`, here `MixedSite`.
+///
+/// ## Example
+/// ```rust
+/// use quote2::{proc_macro2::TokenStream, quote_mixed};
+/// let mut tokens = TokenStream::new();
+/// quote_mixed!(tokens, {
+///     let value = 42;
+/// });
+/// assert_eq!(tokens.to_string(), "let value = 42 ;");
+/// ```
+#[proc_macro]
+pub fn quote_mixed(input: TokenStream) -> TokenStream {
+    expand_quote_mixed(input).unwrap_or_else(|err| err.to_compile_error())
+}
+
+fn expand_quote_mixed(input: TokenStream) -> Result<TokenStream> {
+    let mut input = input.into_iter();
+
+    let target = parse_arg(&mut input, "expected target stream")?;
+    let input = parse_body(&mut input)?;
 
     let mut output = TokenStream::new();
-    expend(input, &mut output, Some(&span), var);
-    output
+    let var = target_var(target);
+    let cx = Cx {
+        site: Some("Mixed"),
+        ..Cx::default()
+    };
+    expend(input, &mut output, cx, var)?;
+    Ok(scope(output))
 }
 
-fn expend(input: TokenStream, o: &mut TokenStream, span: Option<&Ident>, var: Ident) {
+/// Same as [`quote!`], but the fixed tokens of the template are built once per
+/// thread and cloned on later runs, while `#interpolations` and directives are
+/// evaluated every time.
+///
+/// Every run of tokens without interpolations is kept in a thread-local cache,
+/// spanned with `Span::call_site()`. Compiler tokens only live as long as one
+/// macro invocation, so inside a proc macro the cache is bypassed: each run is
+/// parsed from a string instead, which still costs a single call into the
+/// compiler per run instead of one per token. Without the `std` feature the
+/// runs are always parsed.
+///
+/// ## Example
+/// ```rust
+/// use quote2::{proc_macro2::{Ident, TokenStream}, quote_cached};
+/// fn derive_default(name: &Ident) -> TokenStream {
+///     let mut tokens = TokenStream::new();
+///     quote_cached!(tokens, {
+///         impl ::core::default::Default for #name {
+///             fn default() -> Self {
+///                 Self::new()
+///             }
+///         }
+///     });
+///     tokens
+/// }
+/// for name in ["A", "B"] {
+///     let name = quote2::format_ident!("{name}");
+///     let tokens = derive_default(&name);
+///     assert!(tokens.to_string().contains(&format!("for {name}")));
+/// }
+/// ```
+#[proc_macro]
+pub fn quote_cached(input: TokenStream) -> TokenStream {
+    expand_quote_cached(input).unwrap_or_else(|err| err.to_compile_error())
+}
+
+fn expand_quote_cached(input: TokenStream) -> Result<TokenStream> {
+    let mut input = input.into_iter();
+
+    let target = parse_arg(&mut input, "expected target stream")?;
+    let input = parse_body(&mut input)?;
+
+    let mut output = TokenStream::new();
+    let var = target_var(target);
+    let cx = Cx {
+        cached: true,
+        ..Cx::default()
+    };
+    expend(input, &mut output, cx, var)?;
+    Ok(scope(output))
+}
+
+/// [`quote!`] with the extensions that aren't `quote` syntax turned off, used
+/// by `quote2::compat`.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __quote_compat(input: TokenStream) -> TokenStream {
+    let cx = Cx {
+        compat: true,
+        ..Cx::default()
+    };
+    expand_quote(input, cx).unwrap_or_else(|err| err.to_compile_error())
+}
+
+/// [`quote_spanned!`] with the extensions that aren't `quote` syntax turned
+/// off, used by `quote2::compat`.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __quote_spanned_compat(input: TokenStream) -> TokenStream {
+    expand_quote_spanned(input, true).unwrap_or_else(|err| err.to_compile_error())
+}
+
+/// The inverse of [`quote!`]: matches a token stream against a template.
+///
+/// Every `#var` of the template captures the input tokens up to whatever the
+/// template expects next (or up to the end of the enclosing group). A `#var`
+/// directly followed by another `#var` captures a single token tree. All other
+/// tokens must match exactly, spacing is ignored. A `#` that isn't followed by
+/// an ident, like the one of `#[inline]` or of a doc comment, is matched as it
+/// is, `##` matches a single `#`.
+///
+/// Evaluates to `Result<T, quote2::Error>`, where `T` is the bound value for a
+/// single `#var`, or a tuple of all of them in the order they appear. Each
+/// value can be a `TokenStream`, `TokenTree`, `Ident`, `Group`, `Literal` or
+/// `Punct`, depending on the type it is used as. On mismatch, the error is
+/// spanned at the offending token. Repetitions, `#{expr}` and directives are
+/// not supported.
+///
+/// ## Example
+/// ```rust
+/// use quote2::{proc_macro2::{Group, Ident, TokenStream}, unquote};
+/// let input: TokenStream = "fn add(a: u8, b: u8) -> u8 { a + b }".parse().unwrap();
+/// let (name, args, ret, body): (Ident, TokenStream, TokenStream, Group) =
+///     unquote!(input, { fn #name(#args) -> #ret #body }).unwrap();
+///
+/// assert_eq!(name.to_string(), "add");
+/// assert_eq!(args.to_string(), "a : u8 , b : u8");
+/// assert_eq!(ret.to_string(), "u8");
+/// assert_eq!(body.to_string(), "{ a + b }");
+///
+/// let err = unquote!(input, { struct Unit; }).unwrap_err();
+/// assert_eq!(err.to_string(), "expected `struct`");
+/// ```
+#[proc_macro]
+pub fn unquote(input: TokenStream) -> TokenStream {
+    unquote::expand(input).unwrap_or_else(|err| err.to_compile_error())
+}
+
+/// How the fixed tokens of a template are written.
+#[derive(Clone, Copy, Default)]
+struct Cx<'a> {
+    /// The span variable of `quote_spanned!` and `quote_mixed!`.
+    span: Option<&'a Ident>,
+    /// `quote_mixed!`: the `quote2::tt::Site` of unspanned tokens, e.g. `Mixed`.
+    site: Option<&'static str>,
+    /// `quote_cached!`: every static run goes through the thread-local cache.
+    cached: bool,
+    /// `quote2::compat`: `##`, `#{...}` and `@ span` are written as they are,
+    /// like the `quote` crate does.
+    compat: bool,
+}
+
+impl<'a> Cx<'a> {
+    fn spanned(span: &'a Ident) -> Self {
+        Self {
+            span: Some(span),
+            ..Self::default()
+        }
+    }
+}
+
+fn expend(input: TokenStream, o: &mut TokenStream, cx: Cx, var: TokenTree) -> Result<()> {
     let mut input = input.into_iter().peekable();
     let mut items = TokenStream::new();
+    let mut run = Vec::new();
 
     while let Some(tree) = input.next() {
+        if cx.span.is_none() && cx.site.is_none() && is_static(&tree, input.peek()) {
+            run.push(tree);
+            continue;
+        }
+        write_static(&mut items, o, mem::take(&mut run), cx, &var)?;
         match tree {
             TokenTree::Punct(punct) => {
                 let ch = punct.as_char();
-                if ch == '#' && matches!(input.peek(), Some(TokenTree::Ident(_))) {
+                if ch == '#'
+                    && !cx.compat
+                    && matches!(input.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '#')
+                {
+                    // `##` escapes a literal `#`, e.g. `##ident` emits `#ident`.
+                    let Some(TokenTree::Punct(escaped)) = input.next() else {
+                        unreachable!()
+                    };
+                    write_punct(&mut items, &escaped, cx);
+                } else if ch == '#'
+                    && matches!(input.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '#')
+                {
+                    // `quote` writes the `#` of `##x` on its own, then `#x`.
+                    let mut alone = Punct::new('#', Spacing::Alone);
+                    alone.set_span(punct.span());
+                    write_punct(&mut items, &alone, cx);
+                } else if ch == '#'
+                    && matches!(input.peek(), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis)
+                {
                     write_extender(&mut items, o, &var);
-                    let v = input.next();
-
-                    o.extend([
-                        tt(var.clone()),
-                        tt::punct('.'),
-                        tt::ident("add_tokens"),
-                        tt::group('(', |o| {
-                            add(o, tt::punct('&'));
-                            o.extend(v);
-                        }),
-                        tt::punct(';'),
-                    ]);
-                } else {
-                    let varient_ty = match (punct.spacing(), span.is_some()) {
-                        (Spacing::Joint, true) => "punct_join_span",
-                        (Spacing::Alone, true) => "punct_span",
-                        (Spacing::Joint, false) => "punct_join",
-                        (Spacing::Alone, false) => "punct",
+                    let Some(TokenTree::Group(body)) = input.next() else {
+                        unreachable!()
+                    };
+                    let sep = parse_rep_sep(&mut input, &body)?;
+                    repetition(body, sep, o, cx, &var)?;
+                } else if ch == '#'
+                    && !cx.compat
+                    && matches!(input.peek(), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace)
+                {
+                    write_extender(&mut items, o, &var);
+                    let Some(TokenTree::Group(expr)) = input.next() else {
+                        unreachable!()
+                    };
+                    // `#{expr}` is borrowed just like `#ident`, so `#{self.name}`
+                    // doesn't move out of `self`.
+                    let mut expr_group = Group::new(Delimiter::Parenthesis, expr.stream());
+                    expr_group.set_span(expr.span());
+                    add_tokens(o, &var, expr_group);
+                } else if ch == '#' && matches!(input.peek(), Some(TokenTree::Ident(_))) {
+                    write_extender(&mut items, o, &var);
+                    let Some(TokenTree::Ident(name)) = input.next() else {
+                        unreachable!()
                     };
-                    varient(&mut items, varient_ty, |o| {
-                        add_span(o, span);
-                        add(o, tt::char(ch));
-                    });
+                    match name.to_string().as_str() {
+                        "if" => if_directive(name, &mut input, o, cx, &var)?,
+                        "for" => for_directive(name, &mut input, o, cx, &var)?,
+                        "match" => match_directive(name, &mut input, o, cx, &var)?,
+                        "move" => {
+                            let value = match input.next() {
+                                Some(TokenTree::Ident(value)) => value,
+                                found => {
+                                    return Err(Error::unexpected(
+                                        found,
+                                        "expected a variable after `#move`",
+                                    ))
+                                }
+                            };
+                            if span_override(&mut input, cx).is_some() {
+                                return Err(Error::new(
+                                    value.span(),
+                                    "`#move` can't be combined with `@ span`",
+                                ));
+                            }
+                            add_tokens_once(o, &var, value);
+                        }
+                        "else" => {
+                            return Err(Error::new(
+                                name.span(),
+                                "`#else` without a preceding `#if`",
+                            ))
+                        }
+                        _ => match span_override(&mut input, cx) {
+                            Some(to) => add_tokens(o, &var, respan(name, to)),
+                            None => add_tokens(o, &var, name),
+                        },
+                    }
+                } else {
+                    write_punct(&mut items, &punct, cx);
                 }
             }
             TokenTree::Group(group) => {
-                let varient_ty = if span.is_some() {
-                    "group_span"
-                } else {
-                    "group"
-                };
-                varient(&mut items, varient_ty, |o| {
-                    add_span(o, span);
-
-                    let var = Ident::new("__o", Span::call_site());
-                    o.extend([
-                        tt::char(match group.delimiter() {
-                            Delimiter::None => '_',
-                            Delimiter::Brace => '{',
-                            Delimiter::Bracket => '[',
-                            Delimiter::Parenthesis => '(',
-                        }),
-                        tt::punct(','),
-                        tt::punct('|'),
-                        tt(var.clone()),
-                        tt::punct('|'),
-                        tt::group('{', |o| {
-                            expend(group.stream(), o, span, var);
-                        }),
-                    ]);
-                });
-            }
-            TokenTree::Ident(ident) => {
-                let varient_ty = if span.is_some() {
-                    "ident_span"
-                } else {
-                    "ident"
-                };
-                varient(&mut items, varient_ty, |o| {
-                    add_span(o, span);
-                    add(o, Literal::string(&ident.to_string()));
-                });
-            }
-            TokenTree::Literal(lit) => {
-                let varient_ty = if span.is_some() {
-                    "parsed_lit_span"
+                let span_to = span_override(&mut input, cx);
+                write_group(&mut items, group, cx, span_to, &var)?;
+            }
+            TokenTree::Ident(ident) => write_ident(&mut items, &ident, cx),
+            TokenTree::Literal(lit) => write_lit(&mut items, &lit, cx),
+        }
+    }
+    write_static(&mut items, o, mem::take(&mut run), cx, &var)?;
+    write_extender(&mut items, o, &var);
+    Ok(())
+}
+
+fn write_group(
+    items: &mut TokenStream,
+    group: Group,
+    cx: Cx,
+    span_to: Option<TokenStream>,
+    outer: &TokenTree,
+) -> Result<()> {
+    let varient_ty = if cx.span.is_some() || span_to.is_some() {
+        "group_span"
+    } else {
+        "group"
+    };
+    let site = if span_to.is_some() { None } else { cx.site };
+    let var = Ident::new("__o", Span::mixed_site());
+    let mut body = TokenStream::new();
+    expend(group.stream(), &mut body, cx, var.clone().into())?;
+
+    varient(items, site, varient_ty, |o| {
+        // The target picks the token type of the group's content.
+        o.extend([tt::punct('&'), tt(outer.clone()), tt::punct(',')]);
+        match span_to {
+            Some(to) => {
+                o.extend(to);
+                add(o, tt::punct(','));
+            }
+            None => add_span(o, cx.span),
+        }
+        o.extend([
+            tt::char(match group.delimiter() {
+                Delimiter::None => '_',
+                Delimiter::Brace => '{',
+                Delimiter::Bracket => '[',
+                Delimiter::Parenthesis => '(',
+            }),
+            tt::punct(','),
+            tt::punct('|'),
+            tt(var),
+            tt::punct('|'),
+            tt::group('{', |o| o.extend(body)),
+        ]);
+    });
+    Ok(())
+}
+
+/// Whether the identifier is raw is decided now instead of at runtime, the
+/// name is still checked by `Ident::new` when the template runs.
+fn write_ident(items: &mut TokenStream, ident: &Ident, cx: Cx) {
+    let name = ident.to_string();
+    let (varient_ty, name) = match (name.strip_prefix("r#"), cx.span.is_some()) {
+        (Some(name), true) => ("raw_ident_span", name),
+        (Some(name), false) => ("raw_ident", name),
+        (None, true) => ("ident_span", name.as_str()),
+        (None, false) => ("ident", name.as_str()),
+    };
+    varient(items, cx.site, varient_ty, |o| {
+        add_span(o, cx.span);
+        add(o, Literal::string(name));
+    });
+}
+
+fn write_lit(items: &mut TokenStream, lit: &Literal, cx: Cx) {
+    let span = cx.span;
+    match lit::constructor(lit) {
+        Some(ctor) => {
+            let varient_ty = if span.is_some() { "lit_span" } else { "lit" };
+            varient(items, cx.site, varient_ty, |o| {
+                add_span(o, span);
+                code(o, "quote2::tt::Lit::");
+                o.extend([tt::ident(&ctor), tt::group('(', |o| add(o, lit.clone()))]);
+            });
+        }
+        None => {
+            let varient_ty = if span.is_some() {
+                "parsed_lit_span"
+            } else {
+                "parsed_lit"
+            };
+            varient(items, cx.site, varient_ty, |o| {
+                add_span(o, span);
+                add(o, Literal::string(&lit.to_string()));
+            });
+        }
+    }
+}
+
+/// Unspanned runs of at least this many tokens (counting the content of
+/// groups) without interpolations are written as one string that is parsed
+/// at runtime, instead of one call per token. Never without `static-chunks`.
+const STATIC_CHUNK: usize = if cfg!(feature = "static-chunks") {
+    64
+} else {
+    usize::MAX
+};
+
+/// Whether `tree` is written as it is: no interpolation, directive or span
+/// override, all the way down.
+fn is_static(tree: &TokenTree, next: Option<&TokenTree>) -> bool {
+    match tree {
+        TokenTree::Punct(punct) => {
+            punct.as_char() != '#'
+                || !matches!(next, Some(TokenTree::Ident(_)))
+                    && !matches!(next, Some(TokenTree::Punct(p)) if p.as_char() == '#')
+                    && !matches!(next, Some(TokenTree::Group(g)) if matches!(g.delimiter(), Delimiter::Parenthesis | Delimiter::Brace))
+        }
+        TokenTree::Group(group) => {
+            if group.delimiter() == Delimiter::None
+                || matches!(next, Some(TokenTree::Punct(p)) if p.as_char() == '@')
+            {
+                return false;
+            }
+            let mut inner = group.stream().into_iter().peekable();
+            while let Some(tree) = inner.next() {
+                if !is_static(&tree, inner.peek()) {
+                    return false;
+                }
+            }
+            true
+        }
+        _ => true,
+    }
+}
+
+fn count_tokens(trees: &[TokenTree]) -> usize {
+    trees
+        .iter()
+        .map(|tree| match tree {
+            TokenTree::Group(g) => 1 + count_tokens(&g.stream().into_iter().collect::<Vec<_>>()),
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Writes a run of static trees, as one `quote2::tt::parse` call if it is
+/// long enough, or as one `quote2::tt::cached` call in `quote_cached!`.
+fn write_static(
+    items: &mut TokenStream,
+    o: &mut TokenStream,
+    run: Vec<TokenTree>,
+    cx: Cx,
+    var: &TokenTree,
+) -> Result<()> {
+    let min = if cx.cached { 1 } else { STATIC_CHUNK };
+    if run.is_empty() || count_tokens(&run) < min {
+        for tree in run {
+            match tree {
+                TokenTree::Group(group) => write_group(items, group, cx, None, var)?,
+                TokenTree::Ident(ident) => write_ident(items, &ident, cx),
+                TokenTree::Punct(punct) => write_punct(items, &punct, cx),
+                TokenTree::Literal(lit) => write_lit(items, &lit, cx),
+            }
+        }
+        return Ok(());
+    }
+    write_extender(items, o, var);
+    let mut src = String::new();
+    to_source(&mut src, run);
+    o.extend([
+        tt(var.clone()),
+        tt::punct('.'),
+        tt::ident("add_trees"),
+        tt::group('(', |o| {
+            code(
+                o,
+                if cx.cached {
+                    "quote2::tt::cached"
                 } else {
-                    "parsed_lit"
+                    "quote2::tt::parse"
+                },
+            );
+            add(
+                o,
+                tt::group('(', |o| {
+                    o.extend([tt::punct('&'), tt(var.clone()), tt::punct(',')]);
+                    add(o, Literal::string(&src));
+                }),
+            );
+        }),
+        tt::punct(';'),
+    ]);
+    Ok(())
+}
+
+/// Source text that parses back to `trees`, with the same spacing.
+fn to_source(src: &mut String, trees: impl IntoIterator<Item = TokenTree>) {
+    let mut trees = trees.into_iter().peekable();
+    while let Some(tree) = trees.next() {
+        match tree {
+            TokenTree::Group(g) => {
+                let (open, close) = match g.delimiter() {
+                    Delimiter::Parenthesis => ('(', ')'),
+                    Delimiter::Brace => ('{', '}'),
+                    Delimiter::Bracket => ('[', ']'),
+                    Delimiter::None => unreachable!("invisible groups are never static"),
                 };
-                varient(&mut items, varient_ty, |o| {
-                    add_span(o, span);
-                    add(o, Literal::string(&lit.to_string()));
-                });
+                src.push(open);
+                to_source(src, g.stream());
+                src.push(close);
             }
+            TokenTree::Punct(p) => {
+                src.push(p.as_char());
+                // `'` of a lifetime, or the first char of an operator.
+                let glued = p.spacing() == Spacing::Joint
+                    && (p.as_char() == '\'' || matches!(trees.peek(), Some(TokenTree::Punct(_))));
+                if glued {
+                    continue;
+                }
+            }
+            // Not `TokenTree::to_string`, it prints doc comments as plain strings.
+            TokenTree::Literal(lit) => src.push_str(&lit.to_string()),
+            TokenTree::Ident(ident) => src.push_str(&ident.to_string()),
         }
+        src.push(' ');
     }
-    write_extender(&mut items, o, &var);
 }
 
-fn write_extender(items: &mut TokenStream, o: &mut TokenStream, var: &Ident) {
+fn write_punct(items: &mut TokenStream, punct: &Punct, cx: Cx) {
+    let varient_ty = match (punct.spacing(), cx.span.is_some()) {
+        (Spacing::Joint, true) => "punct_join_span",
+        (Spacing::Alone, true) => "punct_span",
+        (Spacing::Joint, false) => "punct_join",
+        (Spacing::Alone, false) => "punct",
+    };
+    varient(items, cx.site, varient_ty, |o| {
+        add_span(o, cx.span);
+        add(o, tt::char(punct.as_char()));
+    });
+}
+
+/// `#if cond { ... } #else if cond { ... } #else { ... }`
+fn if_directive(
+    kw: Ident,
+    input: &mut Peekable<token_stream::IntoIter>,
+    o: &mut TokenStream,
+    cx: Cx,
+    var: &TokenTree,
+) -> Result<()> {
+    let (cond, body) = take_until_block(input, &kw, "expected `{` after `#if` condition")?;
+    add(o, kw);
+    o.extend(cond);
+    add(o, block(body, cx, var)?);
+
+    let mut ahead = input.clone();
+    let has_else = matches!(ahead.next(), Some(TokenTree::Punct(p)) if p.as_char() == '#')
+        && matches!(ahead.next(), Some(TokenTree::Ident(kw)) if kw.to_string() == "else");
+    if !has_else {
+        return Ok(());
+    }
+    input.next();
+    let else_kw = input.next().unwrap();
+    let else_span = else_kw.span();
+    add(o, else_kw);
+    match input.next() {
+        Some(TokenTree::Ident(kw)) if kw.to_string() == "if" => if_directive(kw, input, o, cx, var),
+        Some(TokenTree::Group(body)) if body.delimiter() == Delimiter::Brace => {
+            add(o, block(body, cx, var)?);
+            Ok(())
+        }
+        Some(tt) => Err(Error::new(tt.span(), "expected `{` or `if` after `#else`")),
+        None => Err(Error::new(else_span, "expected `{` or `if` after `#else`")),
+    }
+}
+
+/// `#for pat in iter { ... }`
+fn for_directive(
+    kw: Ident,
+    input: &mut Peekable<token_stream::IntoIter>,
+    o: &mut TokenStream,
+    cx: Cx,
+    var: &TokenTree,
+) -> Result<()> {
+    let (head, body) = take_until_block(input, &kw, "expected `{` after `#for` iterator")?;
+    add(o, kw);
+    o.extend(head);
+    add(o, block(body, cx, var)?);
+    Ok(())
+}
+
+/// `#match value { Pat => { ... } }`, every arm body has to be a block.
+fn match_directive(
+    kw: Ident,
+    input: &mut Peekable<token_stream::IntoIter>,
+    o: &mut TokenStream,
+    cx: Cx,
+    var: &TokenTree,
+) -> Result<()> {
+    let (scrutinee, arms) = take_until_block(input, &kw, "expected `{` after `#match` value")?;
+    add(o, kw);
+    o.extend(scrutinee);
+
+    let end = arms.span_close();
+    let mut lowered = TokenStream::new();
+    let mut arms = arms.stream().into_iter().peekable();
+    while arms.peek().is_some() {
+        loop {
+            match arms.next() {
+                Some(TokenTree::Punct(p))
+                    if p.as_char() == '='
+                        && p.spacing() == Spacing::Joint
+                        && matches!(arms.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '>') =>
+                {
+                    lowered.extend([tt(p), arms.next().unwrap()]);
+                    break;
+                }
+                Some(tt) => lowered.extend([tt]),
+                None => return Err(Error::new(end, "expected `=>` in `#match` arm")),
+            }
+        }
+        match arms.next() {
+            Some(TokenTree::Group(body)) if body.delimiter() == Delimiter::Brace => {
+                add(&mut lowered, block(body, cx, var)?);
+            }
+            Some(tt) => return Err(Error::new(tt.span(), "expected `{` after `=>`")),
+            None => return Err(Error::new(end, "expected `{` after `=>`")),
+        }
+        if matches!(arms.peek(), Some(TokenTree::Punct(p)) if p.as_char() == ',') {
+            arms.next();
+        }
+    }
+    add(o, Group::new(Delimiter::Brace, lowered));
+    Ok(())
+}
+
+/// Takes the tokens of a condition / iterator / scrutinee, up to the block
+/// that follows them.
+fn take_until_block(
+    input: &mut Peekable<token_stream::IntoIter>,
+    kw: &Ident,
+    msg: &str,
+) -> Result<(TokenStream, Group)> {
+    let mut head = TokenStream::new();
+    loop {
+        match input.next() {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => return Ok((head, g)),
+            Some(tt) => head.extend([tt]),
+            None => return Err(Error::new(kw.span(), msg)),
+        }
+    }
+}
+
+/// A template block, lowered into statements that write into `var`.
+fn block(body: Group, cx: Cx, var: &TokenTree) -> Result<Group> {
+    let mut stmts = TokenStream::new();
+    expend(body.stream(), &mut stmts, cx, var.clone())?;
+    let mut block = Group::new(Delimiter::Brace, stmts);
+    block.set_span(body.span());
+    Ok(block)
+}
+
+/// `var.add_tokens(&value);`
+fn add_tokens(o: &mut TokenStream, var: &TokenTree, value: impl Into<TokenTree>) {
+    o.extend([
+        tt(var.clone()),
+        tt::punct('.'),
+        tt::ident("add_tokens"),
+        tt::group('(', |o| {
+            add(o, tt::punct('&'));
+            add(o, value);
+        }),
+        tt::punct(';'),
+    ]);
+}
+
+/// `var.add_tokens_once(value);`, moves `value` instead of borrowing it.
+fn add_tokens_once(o: &mut TokenStream, var: &TokenTree, value: Ident) {
+    o.extend([
+        tt(var.clone()),
+        tt::punct('.'),
+        tt::ident("add_tokens_once"),
+        tt::group('(', |o| add(o, value)),
+        tt::punct(';'),
+    ]);
+}
+
+/// Takes the `@ span` after an interpolation or a group, the span being either
+/// an ident or a parenthesized expression.
+fn span_override(input: &mut Peekable<token_stream::IntoIter>, cx: Cx) -> Option<TokenStream> {
+    if cx.compat {
+        return None;
+    }
+    let mut ahead = input.clone();
+    match ahead.next()? {
+        TokenTree::Punct(p) if p.as_char() == '@' => {}
+        _ => return None,
+    }
+    match ahead.next()? {
+        TokenTree::Ident(_) => {}
+        TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis => {}
+        _ => return None,
+    }
+    input.next();
+    match input.next()? {
+        TokenTree::Group(g) => Some(g.stream()),
+        ident => Some(ident.into()),
+    }
+}
+
+/// `(quote2::tt::Respan(&name, span))`
+fn respan(name: Ident, to: TokenStream) -> TokenTree {
+    tt::group('(', |o| {
+        o.extend([
+            tt::ident("quote2"),
+            tt::punct_joined(':'),
+            tt::punct(':'),
+            tt::ident("tt"),
+            tt::punct_joined(':'),
+            tt::punct(':'),
+            tt::ident("Respan"),
+            tt::group('(', |o| {
+                o.extend([tt::punct('&'), tt(name), tt::punct(',')]);
+                o.extend(to);
+            }),
+        ])
+    })
+}
+
+/// Everything between `#(...)` and `*` is the separator.
+fn parse_rep_sep(
+    input: &mut Peekable<token_stream::IntoIter>,
+    body: &Group,
+) -> Result<TokenStream> {
+    let mut sep = Vec::new();
+    loop {
+        match input.next() {
+            Some(TokenTree::Punct(p)) if p.as_char() == '*' => break,
+            Some(tt) => sep.push(tt),
+            None => return Err(Error::new(body.span(), "expected `*` after repetition")),
+        }
+    }
+    // The last punct is joined with `*` in the source, not with the next item.
+    if let Some(TokenTree::Punct(p)) = sep.last_mut() {
+        let mut alone = Punct::new(p.as_char(), Spacing::Alone);
+        alone.set_span(p.span());
+        *p = alone;
+    }
+    Ok(sep.into_iter().collect())
+}
+
+/// Lowers `#(body) sep *` into a `loop` that advances every interpolated
+/// variable in lock step, stopping at the shortest iterator.
+///
+/// ```text
+/// {
+///     let __has = quote2::rep::NoIter;
+///     let (mut a, __h) = a.quote_into_iter();
+///     let __has = __has | __h;
+///     quote2::rep::check(__has);
+///     let mut __i = 0usize;
+///     loop {
+///         let a = match a.next() { Some(__v) => __v, None => break };
+///         if __i > 0 { <sep> }
+///         __i += 1;
+///         <body>
+///     }
+/// }
+/// ```
+fn repetition(
+    body: Group,
+    sep: TokenStream,
+    o: &mut TokenStream,
+    cx: Cx,
+    var: &TokenTree,
+) -> Result<()> {
+    let mut vars = Vec::new();
+    rep_vars(body.stream(), cx.compat, &mut vars);
+    if vars.is_empty() {
+        return Err(Error::new(
+            body.span(),
+            "expected at least one interpolated variable in repetition",
+        ));
+    }
+    let has_sep = !sep.is_empty();
+    let mut sep_stmts = TokenStream::new();
+    expend(sep, &mut sep_stmts, cx, var.clone())?;
+    let mut body_stmts = TokenStream::new();
+    expend(body.stream(), &mut body_stmts, cx, var.clone())?;
+
+    add(
+        o,
+        tt::group('{', |o| {
+            code(
+                o,
+                "#[allow(unused_imports)] \
+                 use quote2::rep::{RepAsIteratorExt, RepIteratorExt, RepToTokensExt}; \
+                 let __has = quote2::rep::NoIter;",
+            );
+            for v in &vars {
+                code(o, "#[allow(unused_mut)] let");
+                add(
+                    o,
+                    tt::group('(', |o| {
+                        code(o, "mut");
+                        add(o, v.clone());
+                        code(o, ", __h");
+                    }),
+                );
+                add(o, tt::punct('='));
+                add(o, v.clone());
+                code(o, ".quote_into_iter(); let __has = __has | __h;");
+            }
+            code(o, "quote2::rep::check(__has);");
+            if has_sep {
+                code(o, "let mut __i = 0usize;");
+            }
+            code(o, "loop");
+            add(
+                o,
+                tt::group('{', |o| {
+                    for v in &vars {
+                        add(o, tt::ident("let"));
+                        add(o, v.clone());
+                        code(o, "= match");
+                        add(o, v.clone());
+                        code(o, ".next() { Some(__v) => __v, None => break };");
+                    }
+                    if has_sep {
+                        code(o, "if __i > 0");
+                        add(o, tt::group('{', |o| o.extend(sep_stmts)));
+                        code(o, "__i += 1;");
+                    }
+                    o.extend(body_stmts);
+                }),
+            );
+        }),
+    );
+    Ok(())
+}
+
+/// Collects the variables interpolated by a repetition body, including the
+/// ones used by nested repetitions.
+fn rep_vars(input: TokenStream, compat: bool, vars: &mut Vec<Ident>) {
+    let mut input = input.into_iter().peekable();
+    while let Some(tree) = input.next() {
+        match tree {
+            TokenTree::Punct(p) if p.as_char() == '#' => match input.peek() {
+                Some(TokenTree::Punct(p)) if p.as_char() == '#' && !compat => {
+                    input.next();
+                }
+                Some(TokenTree::Ident(v)) if v.to_string() == "move" => {
+                    // `#move x` is collected as `x`.
+                    input.next();
+                    if let Some(TokenTree::Ident(v)) = input.next() {
+                        push_var(vars, v);
+                    }
+                }
+                Some(TokenTree::Ident(v)) if is_directive(v) => {
+                    let mut kw = v.to_string();
+                    input.next();
+                    if kw == "else"
+                        && matches!(input.peek(), Some(TokenTree::Ident(v)) if v.to_string() == "if")
+                    {
+                        input.next();
+                        kw = "if".into();
+                    }
+                    if kw != "else" {
+                        directive_vars(&kw, &mut input, compat, vars);
+                    }
+                }
+                Some(TokenTree::Ident(v)) => {
+                    push_var(vars, v.clone());
+                    input.next();
+                }
+                Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => {
+                    rep_vars(g.stream(), compat, vars);
+                    input.next();
+                }
+                _ => {}
+            },
+            TokenTree::Group(g) => rep_vars(g.stream(), compat, vars),
+            _ => {}
+        }
+    }
+}
+
+/// The variables of a directive's body, without the ones bound by its
+/// patterns: `#for x in ..`, `#if let Some(x) = ..` and `#match` arms.
+fn directive_vars(
+    kw: &str,
+    input: &mut Peekable<token_stream::IntoIter>,
+    compat: bool,
+    vars: &mut Vec<Ident>,
+) {
+    let mut bound = Vec::new();
+    let mut in_pattern = kw == "for";
+    let mut after_dot = false;
+    let body = loop {
+        let tree = match input.next() {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => break g,
+            Some(tree) => tree,
+            None => return,
+        };
+        match &tree {
+            TokenTree::Ident(v) if kw == "for" && v.to_string() == "in" => in_pattern = false,
+            TokenTree::Ident(v) if kw == "if" && v.to_string() == "let" => in_pattern = true,
+            // Not the `=` of `..=`.
+            TokenTree::Punct(p) if kw == "if" && p.as_char() == '=' && !after_dot => {
+                in_pattern = false;
+            }
+            tree if in_pattern => bindings(tree, &mut bound),
+            _ => {}
+        }
+        after_dot = matches!(&tree, TokenTree::Punct(p) if p.as_char() == '.');
+    };
+    if kw != "match" {
+        body_vars(body.stream(), &bound, compat, vars);
+        return;
+    }
+    for tree in body.stream() {
+        match tree {
+            TokenTree::Group(g) if g.delimiter() == Delimiter::Brace => {
+                body_vars(g.stream(), &bound, compat, vars);
+                bound.clear();
+            }
+            tree => bindings(&tree, &mut bound),
+        }
+    }
+}
+
+/// Every ident of a pattern, paths included, which is harmless.
+fn bindings(tree: &TokenTree, bound: &mut Vec<String>) {
+    match tree {
+        TokenTree::Ident(v) => bound.push(v.to_string()),
+        TokenTree::Group(g) => g.stream().into_iter().for_each(|t| bindings(&t, bound)),
+        _ => {}
+    }
+}
+
+fn body_vars(body: TokenStream, bound: &[String], compat: bool, vars: &mut Vec<Ident>) {
+    let mut inner = Vec::new();
+    rep_vars(body, compat, &mut inner);
+    for var in inner {
+        if !bound.contains(&var.to_string()) {
+            push_var(vars, var);
+        }
+    }
+}
+
+fn push_var(vars: &mut Vec<Ident>, var: Ident) {
+    let name = var.to_string();
+    if !vars.iter().any(|var| var.to_string() == name) {
+        vars.push(var);
+    }
+}
+
+fn is_directive(name: &Ident) -> bool {
+    matches!(
+        name.to_string().as_str(),
+        "if" | "else" | "for" | "match" | "move"
+    )
+}
+
+fn write_extender(items: &mut TokenStream, o: &mut TokenStream, var: &TokenTree) {
     if !items.is_empty() {
         let items = mem::take(items);
         o.extend([
             tt(var.clone()),
             tt::punct('.'),
-            tt::ident("extend"),
+            tt::ident("add_trees"),
             tt::group('(', |o| {
                 add(o, Group::new(Delimiter::Bracket, items));
             }),
@@ -188,6 +1292,25 @@ fn write_extender(items: &mut TokenStream, o: &mut TokenStream, var: &Ident) {
     }
 }
 
+/// Appends fixed glue code. It is resolved at the mixed site, so its locals
+/// never clash with the caller's variables.
+fn code(o: &mut TokenStream, src: &str) {
+    o.extend(mixed_site(src.parse().unwrap()));
+}
+
+fn mixed_site(input: TokenStream) -> TokenStream {
+    input
+        .into_iter()
+        .map(|mut tree| {
+            if let TokenTree::Group(g) = &tree {
+                tree = Group::new(g.delimiter(), mixed_site(g.stream())).into();
+            }
+            tree.set_span(Span::mixed_site());
+            tree
+        })
+        .collect()
+}
+
 fn add(o: &mut TokenStream, t: impl Into<TokenTree>) {
     o.extend(Some(t.into()));
 }
@@ -198,7 +1321,14 @@ fn add_span(o: &mut TokenStream, span: Option<&Ident>) {
     }
 }
 
-fn varient(t: &mut TokenStream, varient_ty: &str, f: impl FnOnce(&mut TokenStream)) {
+/// `quote2::tt::varient_ty(...),`, or `quote2::tt::Site::varient_ty(...),`
+/// with a `site`.
+fn varient(
+    t: &mut TokenStream,
+    site: Option<&str>,
+    varient_ty: &str,
+    f: impl FnOnce(&mut TokenStream),
+) {
     t.extend([
         tt::ident("quote2"),
         tt::punct_joined(':'),
@@ -206,10 +1336,11 @@ fn varient(t: &mut TokenStream, varient_ty: &str, f: impl FnOnce(&mut TokenStrea
         tt::ident("tt"),
         tt::punct_joined(':'),
         tt::punct(':'),
-        tt::ident(varient_ty),
-        tt::group('(', f),
-        tt::punct(','),
     ]);
+    if let Some(site) = site {
+        t.extend([tt::ident(site), tt::punct_joined(':'), tt::punct(':')]);
+    }
+    t.extend([tt::ident(varient_ty), tt::group('(', f), tt::punct(',')]);
 }
 
 fn tt<T: Into<TokenTree>>(tt: T) -> TokenTree {
@@ -0,0 +1,95 @@
+use proc_macro::Literal;
+
+const INT_SUFFIXES: [&str; 12] = [
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+];
+
+/// Picks the `proc_macro2::Literal` constructor that rebuilds `lit` from its
+/// own value, e.g. `1u8` -> `Literal::u8_suffixed(1u8)`.
+///
+/// Only literals that the constructor prints back exactly as written qualify.
+/// Returns `None` for anything else (raw strings, escapes, hex, `_`
+/// separators, out of range values, unknown suffixes, ...), those are parsed
+/// from their source instead, so the output keeps the template's text.
+pub fn constructor(lit: &Literal) -> Option<String> {
+    let repr = lit.to_string();
+    let (ctor, content, printable): (_, _, fn(char) -> bool) = match repr.as_bytes() {
+        [b'"', .., b'"'] => ("string", &repr[1..repr.len() - 1], |ch| {
+            ch == '\'' || ch.escape_debug().len() == 1
+        }),
+        [b'\'', .., b'\''] => ("character", &repr[1..repr.len() - 1], |ch| {
+            ch == '"' || ch.escape_debug().len() == 1
+        }),
+        [b'b', b'\'', .., b'\''] => ("byte_character", &repr[2..repr.len() - 1], ascii),
+        [b'b', b'"', .., b'"'] => ("byte_string", &repr[2..repr.len() - 1], ascii),
+        [b'c', b'"', .., b'"'] => ("c_string", &repr[2..repr.len() - 1], ascii),
+        [b'0'..=b'9', ..] => return number(&repr),
+        _ => return None,
+    };
+    // Without escapes the content is the value, which is printed back as is
+    // if no char of it needs an escape.
+    (!content.contains('\\') && content.chars().all(printable)).then(|| ctor.into())
+}
+
+fn ascii(ch: char) -> bool {
+    (ch.is_ascii_graphic() || ch == ' ') && !matches!(ch, '\'' | '"')
+}
+
+fn number(repr: &str) -> Option<String> {
+    if let Some(suffix) = INT_SUFFIXES.into_iter().find(|s| repr.ends_with(s)) {
+        let digits = repr.strip_suffix(suffix).unwrap();
+        return (decimal(digits) && in_range(digits, suffix)).then(|| format!("{suffix}_suffixed"));
+    }
+    if decimal(repr) {
+        return repr
+            .parse::<u128>()
+            .is_ok()
+            .then(|| "u128_unsuffixed".into());
+    }
+    let (digits, suffix) = match repr.strip_suffix("f32").or(repr.strip_suffix("f64")) {
+        Some(digits) => (digits, &repr[digits.len()..]),
+        None => (repr, ""),
+    };
+    let mut printed = match suffix {
+        "f32" => digits.parse::<f32>().ok()?.to_string(),
+        _ => digits.parse::<f64>().ok()?.to_string(),
+    };
+    // Only when printed the same way, `x.0.10` must not become `x.0.1`.
+    let ctor = match suffix {
+        "" => {
+            if !printed.contains('.') {
+                printed.push_str(".0");
+            }
+            "f64_unsuffixed".into()
+        }
+        suffix => format!("{suffix}_suffixed"),
+    };
+    (printed + suffix == repr).then_some(ctor)
+}
+
+/// Plain decimal digits, as the integer constructors print them.
+fn decimal(digits: &str) -> bool {
+    !digits.is_empty()
+        && digits.bytes().all(|b| b.is_ascii_digit())
+        && (digits == "0" || !digits.starts_with('0'))
+}
+
+/// An out of range value would be rejected in the generated code, while the
+/// literal itself is valid (e.g. in a macro input).
+fn in_range(digits: &str, suffix: &str) -> bool {
+    match suffix {
+        "u8" => digits.parse::<u8>().is_ok(),
+        "u16" => digits.parse::<u16>().is_ok(),
+        "u32" => digits.parse::<u32>().is_ok(),
+        "u64" => digits.parse::<u64>().is_ok(),
+        "u128" => digits.parse::<u128>().is_ok(),
+        "i8" => digits.parse::<i8>().is_ok(),
+        "i16" => digits.parse::<i16>().is_ok(),
+        "i32" => digits.parse::<i32>().is_ok(),
+        "i64" => digits.parse::<i64>().is_ok(),
+        "i128" => digits.parse::<i128>().is_ok(),
+        // The target's width is unknown here.
+        "usize" => digits.parse::<u32>().is_ok(),
+        _ => digits.parse::<i32>().is_ok(),
+    }
+}
@@ -0,0 +1,198 @@
+//! `unquote!` lowers a template to calls on a `quote2::parse::Cursor`, one
+//! per template token.
+use crate::{add, code, is_directive, parse_arg, parse_body, tt, Error, Result};
+use proc_macro::*;
+use std::iter::Peekable;
+
+pub fn expand(input: TokenStream) -> Result<TokenStream> {
+    let mut input = input.into_iter();
+
+    let stream = parse_arg(&mut input, "expected input stream")?;
+    let template = parse_body(&mut input)?;
+
+    let m = matcher(0);
+    let mut body = TokenStream::new();
+    code(&mut body, "let mut");
+    add(&mut body, m.clone());
+    code(&mut body, "= quote2::unquote::cursor");
+    add(
+        &mut body,
+        tt::group('(', |o| {
+            add(o, tt::punct('&'));
+            add(o, Group::new(Delimiter::Parenthesis, stream));
+        }),
+    );
+    code(&mut body, ";");
+
+    let mut vars = Vec::new();
+    template_tokens(template, &mut body, &m, 0, &mut vars)?;
+    add(&mut body, m);
+    code(&mut body, ".end()?; ::core::result::Result::Ok");
+    add(
+        &mut body,
+        tt::group('(', |o| match vars.as_slice() {
+            [var] => add(o, var.clone()),
+            vars => add(
+                o,
+                tt::group('(', |o| {
+                    for var in vars {
+                        o.extend([tt(var.clone()), tt::punct(',')]);
+                    }
+                }),
+            ),
+        }),
+    );
+
+    // `(|| -> Result<_, quote2::Error> { ... })()`, so that `?` can be used.
+    let mut o = TokenStream::new();
+    add(
+        &mut o,
+        tt::group('(', |o| {
+            code(o, "|| -> ::core::result::Result<_, quote2::Error>");
+            add(o, Group::new(Delimiter::Brace, body));
+        }),
+    );
+    code(&mut o, "()");
+    Ok(o)
+}
+
+const UNSUPPORTED: &str =
+    "expected `#ident`, repetitions, expressions and directives are not supported by `unquote!`";
+
+fn template_tokens(
+    input: TokenStream,
+    o: &mut TokenStream,
+    m: &Ident,
+    depth: usize,
+    vars: &mut Vec<Ident>,
+) -> Result<()> {
+    let mut input = input.into_iter().peekable();
+    while let Some(tree) = input.next() {
+        match tree {
+            TokenTree::Punct(punct) if punct.as_char() == '#' => match input.peek() {
+                Some(TokenTree::Punct(escaped)) if escaped.as_char() == '#' => {
+                    input.next();
+                    call(o, m, "punct", tt::char('#'));
+                }
+                Some(TokenTree::Ident(name)) if !is_directive(name) => {
+                    let name = name.clone();
+                    input.next();
+                    if vars.iter().any(|var| var.to_string() == name.to_string()) {
+                        return Err(Error::new(
+                            name.span(),
+                            format!("`#{name}` is bound more than once"),
+                        ));
+                    }
+                    // let name = quote2::unquote::bind(&mut m, stop)?;
+                    code(o, "let");
+                    add(o, name.clone());
+                    code(o, "= quote2::unquote::bind");
+                    add(
+                        o,
+                        tt::group('(', |o| {
+                            code(o, "&mut");
+                            o.extend([tt(m.clone()), tt::punct(',')]);
+                            stop(o, &input);
+                        }),
+                    );
+                    code(o, "?;");
+                    vars.push(name);
+                }
+                Some(TokenTree::Ident(_)) => {
+                    return Err(Error::unexpected(input.next(), UNSUPPORTED));
+                }
+                Some(TokenTree::Group(g))
+                    if matches!(g.delimiter(), Delimiter::Parenthesis | Delimiter::Brace) =>
+                {
+                    return Err(Error::unexpected(input.next(), UNSUPPORTED));
+                }
+                // A lone `#`, e.g. of `#[inline]` or a doc comment.
+                _ => call(o, m, "punct", tt::char('#')),
+            },
+            TokenTree::Punct(punct) => call(o, m, "punct", tt::char(punct.as_char())),
+            TokenTree::Ident(ident) => call(o, m, "keyword", Literal::string(&ident.to_string())),
+            TokenTree::Literal(lit) => {
+                // quote2::unquote::lit(&mut m, "repr")?;
+                code(o, "quote2::unquote::lit");
+                add(
+                    o,
+                    tt::group('(', |o| {
+                        code(o, "&mut");
+                        o.extend([
+                            tt(m.clone()),
+                            tt::punct(','),
+                            Literal::string(&lit.to_string()).into(),
+                        ]);
+                    }),
+                );
+                code(o, "?;");
+            }
+            TokenTree::Group(group) if group.delimiter() == Delimiter::None => {
+                template_tokens(group.stream(), o, m, depth, vars)?;
+            }
+            TokenTree::Group(group) => {
+                // let mut inner = m.enter(Delimiter::..)?; ...; inner.end()?;
+                let inner = matcher(depth + 1);
+                code(o, "let mut");
+                add(o, inner.clone());
+                code(o, "=");
+                add(o, m.clone());
+                code(o, ".enter");
+                add(o, tt::group('(', |o| delimiter(o, group.delimiter())));
+                code(o, "?;");
+                template_tokens(group.stream(), o, &inner, depth + 1, vars)?;
+                add(o, inner);
+                code(o, ".end()?;");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn matcher(depth: usize) -> Ident {
+    Ident::new(&format!("__m{depth}"), Span::mixed_site())
+}
+
+/// `m.method(arg)?;`
+fn call(o: &mut TokenStream, m: &Ident, method: &str, arg: impl Into<TokenTree>) {
+    add(o, m.clone());
+    code(o, &format!(".{method}"));
+    add(o, tt::group('(', |o| add(o, arg)));
+    code(o, "?;");
+}
+
+/// `quote2::unquote::Stop::..`, what the template expects after a `#var`.
+fn stop(o: &mut TokenStream, input: &Peekable<token_stream::IntoIter>) {
+    let mut ahead = input.clone();
+    let (variant, arg): (&str, Option<TokenTree>) = match ahead.next() {
+        None => ("End", None),
+        Some(TokenTree::Punct(p)) if p.as_char() == '#' => match ahead.next() {
+            Some(TokenTree::Punct(p)) if p.as_char() == '#' => ("Punct", Some(tt::char('#'))),
+            _ => ("One", None),
+        },
+        Some(TokenTree::Punct(p)) => ("Punct", Some(tt::char(p.as_char()))),
+        Some(TokenTree::Ident(i)) => ("Ident", Some(Literal::string(&i.to_string()).into())),
+        Some(TokenTree::Literal(l)) => ("Lit", Some(Literal::string(&l.to_string()).into())),
+        Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::None => ("One", None),
+        Some(TokenTree::Group(g)) => {
+            code(o, "quote2::unquote::Stop::Group");
+            add(o, tt::group('(', |o| delimiter(o, g.delimiter())));
+            return;
+        }
+    };
+    code(o, &format!("quote2::unquote::Stop::{variant}"));
+    if let Some(arg) = arg {
+        add(o, tt::group('(', |o| add(o, arg)));
+    }
+}
+
+/// `quote2::proc_macro2::Delimiter::..`
+fn delimiter(o: &mut TokenStream, delimiter: Delimiter) {
+    let variant = match delimiter {
+        Delimiter::Brace => "Brace",
+        Delimiter::Bracket => "Bracket",
+        Delimiter::Parenthesis => "Parenthesis",
+        Delimiter::None => "None",
+    };
+    code(o, &format!("quote2::proc_macro2::Delimiter::{variant}"));
+}
@@ -0,0 +1,73 @@
+//! Expression macros with the same syntax as the [`quote`](https://docs.rs/quote)
+//! crate, including `#(...)*` repetition.
+//!
+//! A codebase built on `quote` can be migrated by swapping
+//! `use quote::{quote, quote_spanned};` for `use quote2::compat::{quote, quote_spanned};`,
+//! and then moving hot paths to the write-style [`quote!`](crate::quote!) one by one.
+//!
+//! The templates are written by [`quote!`](crate::quote!) with the extensions
+//! that aren't `quote` syntax turned off, so valid `quote` input means the
+//! same thing here:
+//!
+//! - `##x` is a `#` followed by the interpolation of `x`, not an escaped `#`.
+//! - `#{ ... }` is a `#` followed by a brace group, not an interpolated
+//!   expression.
+//! - `@` after an interpolation or a group is a plain `@`, not a
+//!   [span override](crate::quote!#span-override), so `#name @ Some(_)` is a
+//!   binding pattern.
+//!
+//! The `#for`, `#if`, `#match` and `#move` directives are still
+//! available, `quote` rejects them anyway.
+//!
+//! ```rust
+//! use quote2::compat::{quote, quote_spanned};
+//! use quote2::proc_macro2::Span;
+//!
+//! let fields = ["a", "b"].map(|f| quote2::format_ident!("{f}"));
+//! let body = quote! {
+//!     #(#fields: u8),*
+//! };
+//! let span = Span::call_site();
+//! let tokens = quote_spanned! {span=>
+//!     struct S { #body }
+//! };
+//! assert_eq!(tokens.to_string(), "struct S { a : u8 , b : u8 }");
+//! ```
+
+pub use crate::{
+    __compat_quote as quote, __compat_quote_each_token as quote_each_token,
+    __compat_quote_spanned as quote_spanned,
+};
+
+/// `quote! { ... }` evaluates to a new `TokenStream`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __compat_quote {
+    ($($tt:tt)*) => {{
+        let mut tokens = $crate::proc_macro2::TokenStream::new();
+        $crate::compat::quote_each_token!(tokens $($tt)*);
+        tokens
+    }};
+}
+
+/// `quote_spanned! {span=> ... }` evaluates to a new `TokenStream`, every
+/// token of the template is spanned with `span`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __compat_quote_spanned {
+    ($span:expr=> $($tt:tt)*) => {{
+        let mut tokens = $crate::proc_macro2::TokenStream::new();
+        $crate::__quote_spanned_compat!($span, tokens, { $($tt)* });
+        tokens
+    }};
+}
+
+/// `quote_each_token!(tokens ...)` appends the template to an existing
+/// `TokenStream` variable.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __compat_quote_each_token {
+    ($tokens:ident $($tt:tt)*) => {{
+        $crate::__quote_compat!($tokens, { $($tt)* });
+    }};
+}
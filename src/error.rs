@@ -0,0 +1,147 @@
+use crate::quote_spanned;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::ToTokens;
+
+/// One or more spanned errors, e.g. a token that did not match an
+/// [`unquote!`](crate::unquote) template.
+///
+/// Errors can be [combined](Error::combine) to report all problems at once.
+/// As [`ToTokens`], every message becomes a `::core::compile_error!` at its
+/// span.
+///
+/// ```
+/// use quote2::{proc_macro2::Span, Error, ToTokens};
+/// let mut err = Error::new(Span::call_site(), "unknown attribute `foo`")
+///     .help("supported attributes are `skip` and `rename`");
+/// err.combine(Error::new(Span::call_site(), "missing `name`"));
+///
+/// assert_eq!(
+///     err.to_token_stream().to_string(),
+///     concat!(
+///         ":: core :: compile_error ! { \"unknown attribute `foo`\\n",
+///         "= help: supported attributes are `skip` and `rename`\" } ",
+///         ":: core :: compile_error ! { \"missing `name`\" }"
+///     )
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Error {
+    messages: Vec<Message>,
+}
+
+#[derive(Debug, Clone)]
+struct Message {
+    span: Span,
+    message: String,
+    /// `note: ...` and `help: ...` lines.
+    notes: Vec<String>,
+}
+
+impl Error {
+    pub fn new(span: Span, message: impl fmt::Display) -> Self {
+        Self {
+            messages: vec![Message {
+                span,
+                message: message.to_string(),
+                notes: Vec::new(),
+            }],
+        }
+    }
+
+    /// The span of the first error.
+    pub fn span(&self) -> Span {
+        self.messages[0].span
+    }
+
+    /// Adds a `note: ...` line to the last error.
+    pub fn note(self, note: impl fmt::Display) -> Self {
+        self.annotate("note", note)
+    }
+
+    /// Adds a `help: ...` line to the last error.
+    pub fn help(self, help: impl fmt::Display) -> Self {
+        self.annotate("help", help)
+    }
+
+    fn annotate(mut self, kind: &str, text: impl fmt::Display) -> Self {
+        if let Some(last) = self.messages.last_mut() {
+            last.notes.push(format!("{kind}: {text}"));
+        }
+        self
+    }
+
+    /// Appends the errors of `other`, so they are all reported together.
+    pub fn combine(&mut self, other: Error) {
+        self.messages.extend(other.messages);
+    }
+
+    /// One `::core::compile_error! { "..." }` per error.
+    pub fn to_compile_error(&self) -> TokenStream {
+        self.to_token_stream()
+    }
+}
+
+impl Message {
+    fn text(&self) -> String {
+        let mut text = self.message.clone();
+        for note in &self.notes {
+            text.push_str("\n= ");
+            text.push_str(note);
+        }
+        text
+    }
+}
+
+impl ToTokens for Error {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        for message in &self.messages {
+            let span = message.span;
+            let mut text = Literal::string(&message.text());
+            text.set_span(span);
+            quote_spanned!(span, tokens, {
+                ::core::compile_error! { #text }
+            });
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, message) in self.messages.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            f.write_str(&message.text())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl Extend<Error> for Error {
+    fn extend<I: IntoIterator<Item = Error>>(&mut self, errors: I) {
+        for err in errors {
+            self.combine(err);
+        }
+    }
+}
+
+/// A formatting failure, e.g. from `write!` into a `String`, reported at the
+/// call site.
+impl From<fmt::Error> for Error {
+    fn from(err: fmt::Error) -> Self {
+        Error::new(Span::call_site(), err)
+    }
+}
+
+impl From<Error> for fmt::Error {
+    fn from(_: Error) -> Self {
+        fmt::Error
+    }
+}
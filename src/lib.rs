@@ -1,26 +1,108 @@
 #![doc = include_str!("../README.md")]
+#![no_std]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
 use core::fmt;
 
+extern crate self as quote2;
+
+pub mod compat;
+mod error;
+#[cfg(feature = "proc-macro-native")]
+pub mod native;
+pub mod parse;
+pub mod pretty;
+#[doc(hidden)]
+pub mod rep;
+pub mod sink;
+pub mod testing;
 #[doc(hidden)]
 pub mod tt;
+#[doc(hidden)]
+pub mod unquote;
 pub mod utils;
+pub use error::Error;
 pub use proc_macro2;
 
 use proc_macro2::{TokenStream, TokenTree};
 pub use quote::{format_ident, ToTokens};
-pub use quote2_macros::{quote, quote_spanned};
+#[doc(hidden)]
+pub use quote2_macros::{__quote_compat, __quote_spanned_compat};
+pub use quote2_macros::{quote, quote_cached, quote_mixed, quote_spanned, unquote};
+pub use tt::{ident_escaped, ident_escaped_span};
 
-pub trait Quote: Extend<TokenTree> {
-    fn add_tokens(&mut self, _: impl ToTokens);
+/// A target of [`quote!`], the generated code only calls these two methods.
+///
+/// Implemented for [`TokenStream`], `Vec<TokenTree>` and `&mut T`. Any
+/// `Extend<TokenTree>` buffer can be used through [`sink::Extender`], see
+/// [`sink`] for more.
+pub trait Quote {
+    /// The token type this target is built from, `proc_macro2::TokenTree`
+    /// for all targets but `proc_macro::TokenStream`.
+    type Tree: tt::Token;
+
+    fn add_trees(&mut self, trees: impl IntoIterator<Item = Self::Tree>);
+
+    #[inline]
+    fn add_tokens(&mut self, t: impl ToTokens) {
+        self.add_trees(<Self::Tree as tt::Token>::from_stream(
+            t.into_token_stream(),
+        ));
+    }
+
+    /// Called for `#move x`, which moves `x` instead of borrowing it.
+    #[inline]
+    fn add_tokens_once(&mut self, t: impl ToTokens) {
+        self.add_tokens(t);
+    }
 }
 
 impl Quote for TokenStream {
+    type Tree = TokenTree;
+
+    #[inline]
+    fn add_trees(&mut self, trees: impl IntoIterator<Item = TokenTree>) {
+        self.extend(trees);
+    }
+
     #[inline]
     fn add_tokens(&mut self, t: impl ToTokens) {
         t.to_tokens(self);
     }
 }
 
+impl Quote for Vec<TokenTree> {
+    type Tree = TokenTree;
+
+    #[inline]
+    fn add_trees(&mut self, trees: impl IntoIterator<Item = TokenTree>) {
+        self.extend(trees);
+    }
+}
+
+impl<T: Quote + ?Sized> Quote for &mut T {
+    type Tree = T::Tree;
+
+    #[inline]
+    fn add_trees(&mut self, trees: impl IntoIterator<Item = T::Tree>) {
+        (**self).add_trees(trees);
+    }
+
+    #[inline]
+    fn add_tokens(&mut self, t: impl ToTokens) {
+        (**self).add_tokens(t);
+    }
+
+    #[inline]
+    fn add_tokens_once(&mut self, t: impl ToTokens) {
+        (**self).add_tokens_once(t);
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct QuoteFn<T>(pub T);
 
@@ -55,7 +137,7 @@ impl<F> fmt::Debug for QuoteFn<F> {
     }
 }
 
-impl<T> std::ops::Deref for QuoteFn<T> {
+impl<T> core::ops::Deref for QuoteFn<T> {
     type Target = T;
     #[inline]
     fn deref(&self) -> &Self::Target {
@@ -63,9 +145,102 @@ impl<T> std::ops::Deref for QuoteFn<T> {
     }
 }
 
-impl<T> std::ops::DerefMut for QuoteFn<T> {
+impl<T> core::ops::DerefMut for QuoteFn<T> {
     #[inline]
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
+
+/// A [`QuoteFn`] for closures that can only run once, e.g. because they move
+/// owned data into the output. See [`quote_once`].
+///
+/// # Panics
+///
+/// Interpolating it a second time panics. Interpolate it with `#move name`
+/// to have the compiler reject a second use instead.
+pub struct QuoteFnOnce<F>(Cell<Option<F>>);
+
+/// ```
+/// use quote2::{format_ident, proc_macro2::TokenStream, quote, quote_once};
+/// let fields = vec![format_ident!("a"), format_ident!("b")];
+/// let body = quote_once(move |t| {
+///     for field in fields {
+///         quote!(t, { #field, });
+///     }
+/// });
+/// let mut tokens = TokenStream::new();
+/// quote!(tokens, { [#move body] });
+/// assert_eq!(tokens.to_string(), "[a , b ,]");
+/// ```
+#[inline]
+pub fn quote_once<F>(f: F) -> QuoteFnOnce<F>
+where
+    F: FnOnce(&mut TokenStream),
+{
+    QuoteFnOnce(Cell::new(Some(f)))
+}
+
+impl<F> quote::ToTokens for QuoteFnOnce<F>
+where
+    F: FnOnce(&mut TokenStream),
+{
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self.0.take() {
+            Some(f) => f(tokens),
+            None => panic!("`QuoteFnOnce` was interpolated more than once"),
+        }
+    }
+}
+
+impl<F> fmt::Debug for QuoteFnOnce<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuoteFnOnce").finish()
+    }
+}
+
+/// A [`QuoteFn`] for closures that mutate their state, e.g. a counter or a
+/// non-cloneable iterator. See [`quote_mut`].
+///
+/// # Panics
+///
+/// Interpolating it from within its own closure panics.
+pub struct QuoteFnMut<F>(RefCell<F>);
+
+/// ```
+/// use quote2::{proc_macro2::TokenStream, quote, quote_mut};
+/// let mut n = 0;
+/// let next = quote_mut(move |t| {
+///     n += 1;
+///     quote!(t, { #n });
+/// });
+/// let mut tokens = TokenStream::new();
+/// quote!(tokens, { #next #next });
+/// assert_eq!(tokens.to_string(), "1i32 2i32");
+/// ```
+#[inline]
+pub fn quote_mut<F>(f: F) -> QuoteFnMut<F>
+where
+    F: FnMut(&mut TokenStream),
+{
+    QuoteFnMut(RefCell::new(f))
+}
+
+impl<F> quote::ToTokens for QuoteFnMut<F>
+where
+    F: FnMut(&mut TokenStream),
+{
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let mut f = self
+            .0
+            .try_borrow_mut()
+            .expect("`QuoteFnMut` was interpolated from within itself");
+        f(tokens)
+    }
+}
+
+impl<F> fmt::Debug for QuoteFnMut<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuoteFnMut").finish()
+    }
+}
@@ -0,0 +1,127 @@
+//! Building `proc_macro` tokens directly, with the `proc-macro-native` feature.
+//!
+//! Inside a proc-macro, `quote!` can write straight into a
+//! [`proc_macro::TokenStream`], so the output doesn't have to go through a
+//! `proc_macro2::TokenStream` first. Interpolated values still implement
+//! `ToTokens` and are converted.
+//!
+//! `proc_macro` tokens can only exist while a proc-macro is running. Code that
+//! also runs elsewhere, e.g. in unit tests, can be written for any [`Quote`]
+//! target and expanded with [`expand`], which falls back to `proc_macro2`.
+extern crate proc_macro;
+
+use crate::tt::{impl_lit, Token};
+use crate::{Quote, ToTokens};
+use core::str::FromStr;
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, TokenStream, TokenTree};
+
+pub use proc_macro::is_available;
+
+impl_lit!(Literal);
+
+impl Token for TokenTree {
+    type Stream = TokenStream;
+    type Literal = Literal;
+
+    fn punct(ch: char, spacing: proc_macro2::Spacing, span: Option<proc_macro2::Span>) -> Self {
+        let spacing = match spacing {
+            proc_macro2::Spacing::Joint => Spacing::Joint,
+            proc_macro2::Spacing::Alone => Spacing::Alone,
+        };
+        let mut p = Punct::new(ch, spacing);
+        if let Some(span) = span {
+            p.set_span(span.unwrap());
+        }
+        p.into()
+    }
+
+    fn ident(name: &str, span: proc_macro2::Span) -> Self {
+        Ident::new(name, span.unwrap()).into()
+    }
+
+    fn raw_ident(name: &str, span: proc_macro2::Span) -> Self {
+        Ident::new_raw(name, span.unwrap()).into()
+    }
+
+    fn lit(mut lit: Literal, span: Option<proc_macro2::Span>) -> Self {
+        if let Some(span) = span {
+            lit.set_span(span.unwrap());
+        }
+        lit.into()
+    }
+
+    fn parsed_lit(repr: &str, span: Option<proc_macro2::Span>) -> Self {
+        Self::lit(Literal::from_str(repr).expect("invalid literal"), span)
+    }
+
+    fn group(
+        delimiter: proc_macro2::Delimiter,
+        stream: TokenStream,
+        span: Option<proc_macro2::Span>,
+    ) -> Self {
+        let delimiter = match delimiter {
+            proc_macro2::Delimiter::Parenthesis => Delimiter::Parenthesis,
+            proc_macro2::Delimiter::Brace => Delimiter::Brace,
+            proc_macro2::Delimiter::Bracket => Delimiter::Bracket,
+            proc_macro2::Delimiter::None => Delimiter::None,
+        };
+        let mut g = Group::new(delimiter, stream);
+        if let Some(span) = span {
+            g.set_span(span.unwrap());
+        }
+        g.into()
+    }
+
+    #[inline]
+    fn from_stream(stream: proc_macro2::TokenStream) -> impl IntoIterator<Item = Self> {
+        TokenStream::from(stream)
+    }
+}
+
+impl Quote for TokenStream {
+    type Tree = TokenTree;
+
+    #[inline]
+    fn add_trees(&mut self, trees: impl IntoIterator<Item = TokenTree>) {
+        self.extend(trees);
+    }
+
+    #[inline]
+    fn add_tokens(&mut self, t: impl ToTokens) {
+        self.extend(TokenStream::from(t.into_token_stream()));
+    }
+}
+
+/// Code generation that works with any [`Quote`] target.
+pub trait Template {
+    fn quote<Q: Quote>(&self, tokens: &mut Q);
+}
+
+/// Writes `template` into a `proc_macro::TokenStream` inside a proc-macro,
+/// and into a `proc_macro2::TokenStream` otherwise.
+///
+/// ```
+/// use quote2::{native::{expand, Template}, quote, Quote};
+/// struct Unit(&'static str);
+///
+/// impl Template for Unit {
+///     fn quote<Q: Quote>(&self, tokens: &mut Q) {
+///         let name = quote2::format_ident!("{}", self.0);
+///         quote!(tokens, { struct #name; });
+///     }
+/// }
+///
+/// // Not inside a proc-macro, so `proc_macro2` is used.
+/// assert_eq!(expand(&Unit("A")).to_string(), "struct A ;");
+/// ```
+pub fn expand(template: &impl Template) -> proc_macro2::TokenStream {
+    if is_available() {
+        let mut tokens = TokenStream::new();
+        template.quote(&mut tokens);
+        tokens.into()
+    } else {
+        let mut tokens = proc_macro2::TokenStream::new();
+        template.quote(&mut tokens);
+        tokens
+    }
+}
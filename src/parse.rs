@@ -0,0 +1,298 @@
+//! A minimal cursor over a [`TokenStream`], for macros that only need to peel
+//! off a few idents, puncts and groups.
+//!
+//! ```
+//! use quote2::{parse::Cursor, proc_macro2::{Delimiter, TokenStream}};
+//! let input: TokenStream = "name = [u8; 4] => crate::Out".parse().unwrap();
+//! let mut cursor = Cursor::new(input);
+//! let name = cursor.ident()?;
+//! cursor.punct('=')?;
+//! let array = cursor.group(Delimiter::Bracket)?;
+//! cursor.op("=>")?;
+//! let path = cursor.rest();
+//!
+//! assert_eq!(name, "name");
+//! assert_eq!(array.to_string(), "[u8 ; 4]");
+//! assert_eq!(path.to_string(), "crate :: Out");
+//! # Ok::<_, quote2::Error>(())
+//! ```
+//!
+//! Multi-character operators such as `::`, `=>` and `->` are recognized by
+//! [`Spacing::Joint`], the same way they are written by `quote!`.
+use crate::Error;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use proc_macro2::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+#[derive(Debug, Clone)]
+pub struct Cursor {
+    tokens: Vec<TokenTree>,
+    pos: usize,
+    /// Where "unexpected end of input" errors are reported.
+    end: Span,
+}
+
+impl Cursor {
+    pub fn new(stream: TokenStream) -> Self {
+        Self::with_end(stream, Span::call_site())
+    }
+
+    fn with_end(stream: TokenStream, end: Span) -> Self {
+        Self {
+            tokens: stream.into_iter().collect(),
+            pos: 0,
+            end,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    /// The span of the next token, or of the end of the input.
+    pub fn span(&self) -> Span {
+        self.peek().map_or(self.end, TokenTree::span)
+    }
+
+    /// An error at the next token.
+    pub fn error(&self, message: impl core::fmt::Display) -> Error {
+        Error::new(self.span(), message)
+    }
+
+    pub fn peek(&self) -> Option<&TokenTree> {
+        self.peek_nth(0)
+    }
+
+    pub fn peek_nth(&self, n: usize) -> Option<&TokenTree> {
+        self.tokens.get(self.pos + n)
+    }
+
+    pub fn peek_ident(&self, name: &str) -> bool {
+        matches!(self.peek(), Some(TokenTree::Ident(i)) if i == name)
+    }
+
+    /// Checks for a (possibly multi-character) operator, e.g. `;` or `::`.
+    ///
+    /// Only the spacing between its characters is checked, so `=` also
+    /// matches the start of `=>`. Check longer operators first.
+    pub fn peek_op(&self, op: &str) -> bool {
+        self.op_len(op).is_some()
+    }
+
+    pub fn peek_group(&self, delimiter: Delimiter) -> bool {
+        matches!(self.peek(), Some(TokenTree::Group(g)) if g.delimiter() == delimiter)
+    }
+
+    pub fn peek_lit(&self) -> bool {
+        matches!(self.peek(), Some(TokenTree::Literal(_)))
+    }
+
+    /// Tries several alternatives and reports all of them if none matches.
+    pub fn lookahead(&self) -> Lookahead<'_> {
+        Lookahead {
+            cursor: self,
+            expected: Vec::new(),
+        }
+    }
+
+    fn op_len(&self, op: &str) -> Option<usize> {
+        let mut len = 0;
+        let mut chars = op.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match self.peek_nth(len)? {
+                TokenTree::Punct(p)
+                    if p.as_char() == ch
+                        && (chars.peek().is_none() || p.spacing() == Spacing::Joint) =>
+                {
+                    len += 1
+                }
+                _ => return None,
+            }
+        }
+        (len > 0).then_some(len)
+    }
+
+    fn expected(&self, expected: &str) -> Error {
+        match self.peek() {
+            Some(tt) => Error::new(tt.span(), format_args!("expected {expected}")),
+            None => Error::new(
+                self.end,
+                format_args!("unexpected end of input, expected {expected}"),
+            ),
+        }
+    }
+
+    /// Any token.
+    pub fn token(&mut self) -> Result<TokenTree> {
+        self.next().ok_or_else(|| self.expected("a token"))
+    }
+
+    pub fn ident(&mut self) -> Result<Ident> {
+        match self.peek() {
+            Some(TokenTree::Ident(i)) => {
+                let i = i.clone();
+                self.pos += 1;
+                Ok(i)
+            }
+            _ => Err(self.expected("an identifier")),
+        }
+    }
+
+    /// An identifier with the given name, e.g. `struct`.
+    pub fn keyword(&mut self, name: &str) -> Result<Ident> {
+        match self.peek_ident(name) {
+            true => self.ident(),
+            false => Err(self.expected(&format!("`{name}`"))),
+        }
+    }
+
+    pub fn punct(&mut self, ch: char) -> Result<Punct> {
+        match self.peek() {
+            Some(TokenTree::Punct(p)) if p.as_char() == ch => {
+                let p = p.clone();
+                self.pos += 1;
+                Ok(p)
+            }
+            _ => Err(self.expected(&format!("`{ch}`"))),
+        }
+    }
+
+    /// A (possibly multi-character) operator, e.g. `::`, `=>` or `->`.
+    /// Returns the span of its first character.
+    pub fn op(&mut self, op: &str) -> Result<Span> {
+        match self.op_len(op) {
+            Some(len) => {
+                let span = self.span();
+                self.pos += len;
+                Ok(span)
+            }
+            None => Err(self.expected(&format!("`{op}`"))),
+        }
+    }
+
+    pub fn group(&mut self, delimiter: Delimiter) -> Result<Group> {
+        match self.peek() {
+            Some(TokenTree::Group(g)) if g.delimiter() == delimiter => {
+                let g = g.clone();
+                self.pos += 1;
+                Ok(g)
+            }
+            _ => Err(self.expected(describe(delimiter))),
+        }
+    }
+
+    /// Enters a group: a cursor over its content, which reports unexpected
+    /// end of input at the closing delimiter.
+    pub fn enter(&mut self, delimiter: Delimiter) -> Result<Cursor> {
+        let group = self.group(delimiter)?;
+        Ok(Cursor::with_end(group.stream(), group.span_close()))
+    }
+
+    pub fn lit(&mut self) -> Result<Literal> {
+        match self.peek() {
+            Some(TokenTree::Literal(l)) => {
+                let l = l.clone();
+                self.pos += 1;
+                Ok(l)
+            }
+            _ => Err(self.expected("a literal")),
+        }
+    }
+
+    /// All the remaining tokens.
+    pub fn rest(&mut self) -> TokenStream {
+        self.by_ref().collect()
+    }
+
+    /// Fails if there are any tokens left.
+    pub fn end(&self) -> Result<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(tt) => Err(Error::new(
+                tt.span(),
+                format_args!("unexpected token `{tt}`"),
+            )),
+        }
+    }
+}
+
+impl From<TokenStream> for Cursor {
+    fn from(stream: TokenStream) -> Self {
+        Self::new(stream)
+    }
+}
+
+impl Iterator for Cursor {
+    type Item = TokenTree;
+
+    fn next(&mut self) -> Option<TokenTree> {
+        let tt = self.tokens.get(self.pos)?.clone();
+        self.pos += 1;
+        Some(tt)
+    }
+}
+
+fn describe(delimiter: Delimiter) -> &'static str {
+    match delimiter {
+        Delimiter::Parenthesis => "`( ... )`",
+        Delimiter::Brace => "`{ ... }`",
+        Delimiter::Bracket => "`[ ... ]`",
+        Delimiter::None => "a group",
+    }
+}
+
+/// Collects the alternatives that were checked, see [`Cursor::lookahead`].
+///
+/// ```
+/// use quote2::{parse::Cursor, proc_macro2::TokenStream};
+/// let mut cursor = Cursor::new("enum".parse::<TokenStream>().unwrap());
+/// let mut lookahead = cursor.lookahead();
+/// if lookahead.peek_ident("struct") {
+///     // ...
+/// } else if lookahead.peek_op("::") {
+///     // ...
+/// } else {
+///     let err = lookahead.error();
+///     assert_eq!(err.to_string(), "expected `struct` or `::`");
+/// }
+/// ```
+pub struct Lookahead<'a> {
+    cursor: &'a Cursor,
+    expected: Vec<String>,
+}
+
+impl Lookahead<'_> {
+    pub fn peek_ident(&mut self, name: &str) -> bool {
+        self.expected.push(format!("`{name}`"));
+        self.cursor.peek_ident(name)
+    }
+
+    pub fn peek_op(&mut self, op: &str) -> bool {
+        self.expected.push(format!("`{op}`"));
+        self.cursor.peek_op(op)
+    }
+
+    pub fn peek_group(&mut self, delimiter: Delimiter) -> bool {
+        self.expected.push(describe(delimiter).into());
+        self.cursor.peek_group(delimiter)
+    }
+
+    pub fn peek_lit(&mut self) -> bool {
+        self.expected.push("a literal".into());
+        self.cursor.peek_lit()
+    }
+
+    /// `expected one of ...`, at the next token.
+    pub fn error(self) -> Error {
+        let expected = match self.expected.as_slice() {
+            [] => "something else".into(),
+            [one] => one.clone(),
+            [a, b] => format!("{a} or {b}"),
+            [rest @ .., last] => format!("one of {}, or {last}", rest.join(", ")),
+        };
+        self.cursor.expected(&expected)
+    }
+}
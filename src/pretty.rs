@@ -0,0 +1,359 @@
+//! A small Rust pretty printer for generated token streams.
+//!
+//! [`print`] lays out items, blocks, match arms and attributes one per line,
+//! indented by block, without depending on `syn` or `prettyplease`. It is
+//! meant for debugging a code generator or writing the output of a `build.rs`,
+//! not to replace `rustfmt`: long lines are never wrapped.
+//!
+//! ```
+//! use quote2::{proc_macro2::TokenStream, quote};
+//! let mut tokens = TokenStream::new();
+//! quote!(tokens, {
+//!     /// Doc
+//!     fn f(x: Option<u8>) -> u8 { match x { Some(x) => x, None => 0 } }
+//! });
+//! assert_eq!(
+//!     quote2::pretty::print(&tokens),
+//!     r#"#[doc = " Doc"]
+//! fn f(x: Option<u8>) -> u8 {
+//!     match x {
+//!         Some(x) => x,
+//!         None => 0
+//!     }
+//! }
+//! "#
+//! );
+//! ```
+//!
+//! Tokens are never added or removed, doc comments are printed in their
+//! `#[doc = "..."]` form and no trailing comma is inserted. Parsing the
+//! output yields a stream equal to the input, up to the spacing of
+//! punctuation and invisible groups, see
+//! [`assert_tokens_eq!`](crate::testing::assert_tokens_eq).
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// Formats `tokens` as Rust source, one item or statement per line.
+pub fn print(tokens: &TokenStream) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        indent: 0,
+        prev: Prev::LineStart,
+        generics: 0,
+        closure: false,
+    };
+    printer.stream(tokens, true);
+    printer.newline();
+    printer.out
+}
+
+/// Iterates the trees of `stream`, looking through invisible groups.
+pub(crate) fn flatten(stream: &TokenStream) -> Vec<TokenTree> {
+    let mut trees = Vec::new();
+    for tt in stream.clone() {
+        match tt {
+            TokenTree::Group(g) if g.delimiter() == Delimiter::None => {
+                trees.extend(flatten(&g.stream()));
+            }
+            tt => trees.push(tt),
+        }
+    }
+    trees
+}
+
+/// Keywords that are followed by a space, even before a `(` or `[`.
+const KEYWORDS: [&str; 22] = [
+    "as", "async", "await", "break", "const", "dyn", "else", "for", "if", "impl", "in", "let",
+    "loop", "match", "move", "mut", "ref", "return", "static", "unsafe", "where", "while",
+];
+
+/// What was printed last, to decide the spacing of the next token.
+#[derive(Clone, PartialEq)]
+enum Prev {
+    LineStart,
+    Open,
+    Close,
+    Ident { keyword: bool, generic: bool },
+    Lit { int: bool },
+    Punct { ch: char, joint: bool, glue: bool },
+}
+
+struct Printer {
+    out: String,
+    indent: usize,
+    prev: Prev,
+    /// Open `<` of generic arguments.
+    generics: usize,
+    /// Inside the `|...|` parameters of a closure.
+    closure: bool,
+}
+
+impl Printer {
+    /// `block` is true at the top level and in braces, where `;` and `,`
+    /// end the line.
+    fn stream(&mut self, stream: &TokenStream, block: bool) {
+        let trees = flatten(stream);
+        let mut attr = false;
+        let mut where_clause = false;
+        for (i, tt) in trees.iter().enumerate() {
+            match tt {
+                TokenTree::Group(g) if g.delimiter() == Delimiter::Brace => {
+                    let inner = g.stream();
+                    let path = matches!(
+                        self.prev,
+                        Prev::Punct {
+                            ch: ':',
+                            glue: true,
+                            ..
+                        }
+                    );
+                    if inner.is_empty() || path {
+                        self.word("{", !path && self.prev != Prev::Open);
+                        self.prev = Prev::Open;
+                        self.stream(&inner, false);
+                        self.word("}", false);
+                    } else {
+                        self.word("{", self.prev != Prev::Open);
+                        self.newline();
+                        self.indent += 1;
+                        let generics = core::mem::take(&mut self.generics);
+                        self.stream(&inner, true);
+                        self.generics = generics;
+                        self.newline();
+                        self.indent -= 1;
+                        self.word("}", false);
+                    }
+                    self.prev = Prev::Close;
+                    where_clause = false;
+                    let continues = match trees.get(i + 1) {
+                        Some(TokenTree::Punct(p)) => {
+                            matches!(p.as_char(), ',' | ';' | '.' | '?' | '=')
+                        }
+                        Some(TokenTree::Ident(ident)) => ident == "else",
+                        _ => false,
+                    };
+                    if block && !continues {
+                        self.newline();
+                    }
+                }
+                TokenTree::Group(g) => {
+                    let (open, close) = match g.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        _ => ("[", "]"),
+                    };
+                    let space = match &self.prev {
+                        Prev::Ident { keyword, .. } => *keyword,
+                        Prev::Punct { glue, .. } => !glue,
+                        Prev::Lit { .. } => true,
+                        _ => false,
+                    };
+                    self.word(open, space);
+                    self.prev = Prev::Open;
+                    let (generics, closure) = (self.generics, self.closure);
+                    (self.generics, self.closure) = (0, false);
+                    self.stream(&g.stream(), false);
+                    (self.generics, self.closure) = (generics, closure);
+                    self.word(close, false);
+                    self.prev = Prev::Close;
+                    if core::mem::take(&mut attr) {
+                        self.newline();
+                    }
+                }
+                TokenTree::Ident(ident) => {
+                    attr = false;
+                    let text = ident.to_string();
+                    where_clause |= text == "where";
+                    let space = self.space_before_word();
+                    self.word(&text, space);
+                    let keyword = KEYWORDS.contains(&text.as_str());
+                    let generic = text.starts_with(|c: char| c.is_ascii_uppercase())
+                        || matches!(text.as_str(), "impl" | "fn" | "struct" | "enum" | "union")
+                        || matches!(text.as_str(), "trait" | "type")
+                        || self.item_name();
+                    self.prev = Prev::Ident { keyword, generic };
+                }
+                TokenTree::Literal(lit) => {
+                    attr = false;
+                    let text = lit.to_string();
+                    let space = self.space_before_word();
+                    self.word(&text, space);
+                    let int = text.starts_with(|c: char| c.is_ascii_digit())
+                        && !text.contains(['.', 'e', 'E'])
+                        || text.starts_with("0x");
+                    self.prev = Prev::Lit { int };
+                }
+                TokenTree::Punct(p) => {
+                    let ch = p.as_char();
+                    let joint = p.spacing() == Spacing::Joint;
+                    let at_line_start = self.prev == Prev::LineStart;
+                    let generics = self.generics;
+                    let (space, glue) = self.punct(ch, joint, trees.get(i + 1));
+                    self.word(ch.encode_utf8(&mut [0; 4]), space);
+                    self.prev = Prev::Punct { ch, joint, glue };
+                    if ch == '>' && self.generics < generics {
+                        // Printed like a closing group: `Vec<u8>::new()`.
+                        self.prev = Prev::Close;
+                    }
+                    attr = (ch == '#' && at_line_start && block) || (attr && ch == '!');
+                    where_clause &= ch != ';';
+                    let ends_line = match ch {
+                        ';' => true,
+                        ',' => self.generics == 0 && !self.closure && !where_clause,
+                        _ => false,
+                    };
+                    if block && ends_line {
+                        self.newline();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether the ident just printed follows `fn`, `struct`, ..., so that a
+    /// `<` after it opens generic parameters.
+    fn item_name(&self) -> bool {
+        matches!(self.prev, Prev::Ident { generic: true, .. })
+            && !matches!(self.prev, Prev::Ident { keyword: true, .. })
+    }
+
+    fn space_before_word(&self) -> bool {
+        match self.prev {
+            Prev::LineStart | Prev::Open => false,
+            Prev::Punct { joint, glue, .. } => !joint && !glue,
+            _ => true,
+        }
+    }
+
+    /// The space before `ch`, and whether the next token is glued to it.
+    fn punct(&mut self, ch: char, joint: bool, next: Option<&TokenTree>) -> (bool, bool) {
+        let prev = self.prev.clone();
+        let after_operand = matches!(prev, Prev::Ident { keyword: false, .. } | Prev::Lit { .. })
+            || prev == Prev::Close
+            || matches!(prev, Prev::Punct { ch: '?', .. });
+        let start = match prev {
+            Prev::LineStart | Prev::Open => true,
+            Prev::Punct {
+                joint: prev_joint,
+                glue,
+                ch: prev_ch,
+            } => {
+                if prev_joint {
+                    // `//` and `/*` would start a comment.
+                    let comment = prev_ch == '/' && matches!(ch, '/' | '*');
+                    return (comment, self.glue_after(ch, joint, false, next));
+                }
+                glue
+            }
+            _ => false,
+        };
+        let space = !start
+            && match ch {
+                ',' | ';' | '?' => false,
+                // An integer followed by `.` and a literal would be read back
+                // as a float: `x.0 .1`.
+                '.' => {
+                    !after_operand
+                        || prev == (Prev::Lit { int: true })
+                            && matches!(next, Some(TokenTree::Literal(_)))
+                }
+                ':' if joint => !after_operand || matches!(prev, Prev::Ident { keyword: true, .. }),
+                ':' => false,
+                // `!=` is an operator, a `!` after an ident a macro call.
+                '!' => joint || !matches!(prev, Prev::Ident { keyword: false, .. }),
+                '<' if self.generic_open(&prev) => false,
+                '>' if self.generics > 0 => false,
+                '|' if self.closure => false,
+                _ => true,
+            };
+        let glue = self.glue_after(ch, joint, !after_operand, next);
+        (space, glue)
+    }
+
+    /// Whether the token after `ch` is glued to it, and tracks `<...>` and
+    /// `|...|` nesting.
+    fn glue_after(&mut self, ch: char, joint: bool, unary: bool, next: Option<&TokenTree>) -> bool {
+        let prev = self.prev.clone();
+        let joint_prev = matches!(prev, Prev::Punct { joint: true, .. });
+        match ch {
+            '.' | '#' | '$' | '\'' => true,
+            ':' => matches!(
+                prev,
+                Prev::Punct {
+                    ch: ':',
+                    joint: true,
+                    ..
+                }
+            ),
+            '!' => !joint,
+            // `..=`
+            '=' => matches!(
+                prev,
+                Prev::Punct {
+                    ch: '.',
+                    joint: true,
+                    ..
+                }
+            ),
+            '&' | '*' | '-' => unary && !joint_prev,
+            '<' if self.generic_open(&prev) => {
+                self.generics += 1;
+                true
+            }
+            '>' if self.generics > 0
+                && !matches!(
+                    prev,
+                    Prev::Punct {
+                        ch: '-' | '=',
+                        joint: true,
+                        ..
+                    }
+                ) =>
+            {
+                self.generics -= 1;
+                false
+            }
+            '|' if self.closure => {
+                self.closure = false;
+                false
+            }
+            '|' if unary && !joint && !joint_prev => {
+                // `||` is printed as is.
+                self.closure = !matches!(next, Some(TokenTree::Punct(p)) if p.as_char() == '|');
+                self.closure
+            }
+            _ => false,
+        }
+    }
+
+    fn generic_open(&self, prev: &Prev) -> bool {
+        matches!(prev, Prev::Ident { generic: true, .. })
+            || matches!(
+                prev,
+                Prev::Punct {
+                    ch: ':',
+                    glue: true,
+                    ..
+                }
+            )
+            || (self.generics > 0 && matches!(prev, Prev::Ident { .. }))
+    }
+
+    fn word(&mut self, word: &str, space: bool) {
+        if self.prev == Prev::LineStart {
+            for _ in 0..self.indent {
+                self.out.push_str("    ");
+            }
+        } else if space {
+            self.out.push(' ');
+        }
+        self.out.push_str(word);
+    }
+
+    fn newline(&mut self) {
+        if self.prev != Prev::LineStart {
+            self.out.push('\n');
+            self.prev = Prev::LineStart;
+        }
+    }
+}
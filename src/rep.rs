@@ -0,0 +1,126 @@
+//! Runtime support for `#(...)*` repetitions.
+//!
+//! Every interpolated variable inside a repetition is passed through
+//! `quote_into_iter`, which resolves (via autoref method resolution) to one of
+//! the extension traits below. Iterators and collections are iterated,
+//! everything else that implements `ToTokens` is repeated as is.
+use alloc::collections::{btree_set, BTreeSet};
+use alloc::vec::Vec;
+use core::ops::BitOr;
+use core::slice;
+use quote::ToTokens;
+
+pub struct HasIter;
+pub struct NoIter;
+
+impl BitOr<HasIter> for HasIter {
+    type Output = HasIter;
+    #[inline]
+    fn bitor(self, _: HasIter) -> HasIter {
+        HasIter
+    }
+}
+
+impl BitOr<NoIter> for HasIter {
+    type Output = HasIter;
+    #[inline]
+    fn bitor(self, _: NoIter) -> HasIter {
+        HasIter
+    }
+}
+
+impl BitOr<HasIter> for NoIter {
+    type Output = HasIter;
+    #[inline]
+    fn bitor(self, _: HasIter) -> HasIter {
+        HasIter
+    }
+}
+
+impl BitOr<NoIter> for NoIter {
+    type Output = NoIter;
+    #[inline]
+    fn bitor(self, _: NoIter) -> NoIter {
+        NoIter
+    }
+}
+
+pub trait RepIteratorExt: Iterator + Sized {
+    #[inline]
+    fn quote_into_iter(self) -> (Self, HasIter) {
+        (self, HasIter)
+    }
+}
+
+impl<T: Iterator> RepIteratorExt for T {}
+
+pub trait RepToTokensExt {
+    #[inline]
+    fn next(&self) -> Option<&Self> {
+        Some(self)
+    }
+
+    #[inline]
+    fn quote_into_iter(&self) -> (&Self, NoIter) {
+        (self, NoIter)
+    }
+}
+
+impl<T: ToTokens + ?Sized> RepToTokensExt for T {}
+
+pub trait RepAsIteratorExt<'q> {
+    type Iter: Iterator;
+    fn quote_into_iter(&'q self) -> (Self::Iter, HasIter);
+}
+
+impl<'q, T: RepAsIteratorExt<'q> + ?Sized> RepAsIteratorExt<'q> for &T {
+    type Iter = T::Iter;
+    #[inline]
+    fn quote_into_iter(&'q self) -> (Self::Iter, HasIter) {
+        <T as RepAsIteratorExt>::quote_into_iter(*self)
+    }
+}
+
+impl<'q, T: RepAsIteratorExt<'q> + ?Sized> RepAsIteratorExt<'q> for &mut T {
+    type Iter = T::Iter;
+    #[inline]
+    fn quote_into_iter(&'q self) -> (Self::Iter, HasIter) {
+        <T as RepAsIteratorExt>::quote_into_iter(*self)
+    }
+}
+
+impl<'q, T: 'q> RepAsIteratorExt<'q> for [T] {
+    type Iter = slice::Iter<'q, T>;
+    #[inline]
+    fn quote_into_iter(&'q self) -> (Self::Iter, HasIter) {
+        (self.iter(), HasIter)
+    }
+}
+
+impl<'q, T: 'q, const N: usize> RepAsIteratorExt<'q> for [T; N] {
+    type Iter = slice::Iter<'q, T>;
+    #[inline]
+    fn quote_into_iter(&'q self) -> (Self::Iter, HasIter) {
+        (self.iter(), HasIter)
+    }
+}
+
+impl<'q, T: 'q> RepAsIteratorExt<'q> for Vec<T> {
+    type Iter = slice::Iter<'q, T>;
+    #[inline]
+    fn quote_into_iter(&'q self) -> (Self::Iter, HasIter) {
+        (self.iter(), HasIter)
+    }
+}
+
+impl<'q, T: 'q> RepAsIteratorExt<'q> for BTreeSet<T> {
+    type Iter = btree_set::Iter<'q, T>;
+    #[inline]
+    fn quote_into_iter(&'q self) -> (Self::Iter, HasIter) {
+        (self.iter(), HasIter)
+    }
+}
+
+/// Ensures that at least one interpolated value of a repetition is iterable.
+#[inline]
+pub fn check(_: HasIter) {}
@@ -0,0 +1,155 @@
+//! [`Quote`] targets other than [`TokenStream`](proc_macro2::TokenStream).
+use crate::Quote;
+use alloc::string::ToString;
+use core::hash::{Hash, Hasher};
+use proc_macro2::{Delimiter, Spacing, TokenTree};
+
+/// Makes any `Extend<TokenTree>` buffer a target of `quote!`.
+///
+/// ```
+/// use quote2::{proc_macro2::TokenTree, quote, sink::Extender};
+/// let mut buf = Extender(std::collections::VecDeque::<TokenTree>::new());
+/// quote!(buf, { a + b });
+/// assert_eq!(buf.len(), 3);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Extender<T>(pub T);
+
+impl<T: Extend<TokenTree>> Quote for Extender<T> {
+    type Tree = TokenTree;
+
+    #[inline]
+    fn add_trees(&mut self, trees: impl IntoIterator<Item = TokenTree>) {
+        self.0.extend(trees);
+    }
+}
+
+impl<T> core::ops::Deref for Extender<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> core::ops::DerefMut for Extender<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Counts and hashes the tokens written to it, without storing them.
+///
+/// Tokens inside groups are counted too. Spans are ignored, so two outputs
+/// with the same hash print the same code (modulo hash collisions). Useful
+/// to compare or cache generated code cheaply.
+///
+/// ```
+/// use quote2::{quote, sink::TokenHasher};
+/// let mut a = TokenHasher::new();
+/// quote!(a, { fn f() {} });
+/// let mut b = TokenHasher::new();
+/// quote!(b, { fn f() {} });
+///
+/// assert_eq!(a.count(), 4);
+/// assert_eq!(a.finish(), b.finish());
+/// ```
+#[derive(Debug, Clone)]
+pub struct TokenHasher<H = Fnv1a> {
+    hasher: H,
+    count: usize,
+}
+
+impl TokenHasher {
+    pub fn new() -> Self {
+        Self::with_hasher(Fnv1a::default())
+    }
+}
+
+impl Default for TokenHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: Hasher> TokenHasher<H> {
+    pub fn with_hasher(hasher: H) -> Self {
+        Self { hasher, count: 0 }
+    }
+
+    /// The number of tokens written so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    fn tree(&mut self, tt: TokenTree) {
+        self.count += 1;
+        match tt {
+            TokenTree::Group(g) => {
+                0u8.hash(&mut self.hasher);
+                match g.delimiter() {
+                    Delimiter::Parenthesis => '(',
+                    Delimiter::Brace => '{',
+                    Delimiter::Bracket => '[',
+                    Delimiter::None => '_',
+                }
+                .hash(&mut self.hasher);
+                self.add_trees(g.stream());
+                // Marks the end of the group, so `(a) b` and `(a b)` differ.
+                4u8.hash(&mut self.hasher);
+            }
+            TokenTree::Ident(i) => {
+                1u8.hash(&mut self.hasher);
+                i.to_string().hash(&mut self.hasher);
+            }
+            TokenTree::Punct(p) => {
+                2u8.hash(&mut self.hasher);
+                p.as_char().hash(&mut self.hasher);
+                (p.spacing() == Spacing::Joint).hash(&mut self.hasher);
+            }
+            TokenTree::Literal(l) => {
+                3u8.hash(&mut self.hasher);
+                l.to_string().hash(&mut self.hasher);
+            }
+        }
+    }
+}
+
+impl<H: Hasher> Quote for TokenHasher<H> {
+    type Tree = TokenTree;
+
+    fn add_trees(&mut self, trees: impl IntoIterator<Item = TokenTree>) {
+        for tt in trees {
+            self.tree(tt);
+        }
+    }
+}
+
+/// The 64-bit FNV-1a hash, the default hasher of [`TokenHasher`].
+///
+/// It doesn't need `std` and gives the same result on every run and platform.
+#[derive(Debug, Clone)]
+pub struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
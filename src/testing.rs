@@ -0,0 +1,252 @@
+//! Assertions for tests of code generators.
+//!
+//! [`assert_tokens_eq!`] compares token streams tree by tree instead of by
+//! their `to_string()`. On mismatch it reports the first divergent token with
+//! the groups it is nested in, followed by a line diff of both streams.
+//!
+//! ```
+//! use quote2::{proc_macro2::TokenStream, quote, testing::assert_tokens_eq};
+//! let name = quote2::format_ident!("answer");
+//! let mut tokens = TokenStream::new();
+//! quote!(tokens, {
+//!     fn #name() -> u8 { 42 }
+//! });
+//! assert_tokens_eq!(tokens, {
+//!     fn answer() -> u8 {
+//!         42
+//!     }
+//! });
+//! ```
+//!
+//! [`snapshot!`] (with the `std` feature) compares the [pretty printed](crate::pretty)
+//! output of a generator against a file in `tests/snapshots/`.
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+
+use crate::pretty::{flatten, print};
+
+pub use crate::__assert_tokens_eq as assert_tokens_eq;
+#[cfg(feature = "std")]
+pub use crate::__snapshot as snapshot;
+
+/// `assert_tokens_eq!(actual, { template })` or `assert_tokens_eq!(actual, expected)`.
+///
+/// The template is written like the body of [`quote!`](crate::quote), and can
+/// interpolate `#vars`. Spacing and invisible groups are ignored, and so are
+/// spans unless `spans` is passed as a third argument:
+/// `assert_tokens_eq!(actual, expected, spans)`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __assert_tokens_eq {
+    ($actual:expr, { $($tt:tt)* } $(, $spans:ident)? $(,)?) => {{
+        let mut expected = $crate::proc_macro2::TokenStream::new();
+        $crate::quote!(expected, { $($tt)* });
+        $crate::__assert_tokens_eq!($actual, expected $(, $spans)?);
+    }};
+    ($actual:expr, $expected:expr, spans $(,)?) => {
+        $crate::testing::assert_eq(&$actual, &$expected, true)
+    };
+    ($actual:expr, $expected:expr $(,)?) => {
+        $crate::testing::assert_eq(&$actual, &$expected, false)
+    };
+}
+
+/// `snapshot!(name, tokens)` compares the pretty printed `tokens` against
+/// `tests/snapshots/<name>.rs`, relative to the crate being tested.
+///
+/// On mismatch, or if the snapshot does not exist yet, the output is written
+/// to `tests/snapshots/<name>.rs.new` and the test fails with a line diff.
+/// Run the tests with `QUOTE2_BLESS=1` to write the snapshots instead.
+///
+/// ```no_run
+/// use quote2::{proc_macro2::TokenStream, quote, testing::snapshot};
+/// let mut tokens = TokenStream::new();
+/// quote!(tokens, { struct Point { x: u8, y: u8 } });
+/// snapshot!("point", tokens);
+/// ```
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __snapshot {
+    ($name:expr, $tokens:expr $(,)?) => {
+        $crate::testing::snapshot(::core::env!("CARGO_MANIFEST_DIR"), $name, &$tokens)
+    };
+}
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[track_caller]
+pub fn snapshot(manifest_dir: &str, name: &str, tokens: &impl quote::ToTokens) {
+    let bless = std::env::var("QUOTE2_BLESS").is_ok_and(|v| !v.is_empty() && v != "0");
+    snapshot_with(manifest_dir, name, tokens, bless);
+}
+
+/// [`snapshot`] with bless mode given instead of read from `QUOTE2_BLESS`.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[track_caller]
+pub fn snapshot_with(manifest_dir: &str, name: &str, tokens: &impl quote::ToTokens, bless: bool) {
+    use std::{fs, path::Path};
+
+    let actual = print(&tokens.to_token_stream());
+    let dir = Path::new(manifest_dir).join("tests").join("snapshots");
+    let path = dir.join(format!("{name}.rs"));
+    let new = dir.join(format!("{name}.rs.new"));
+
+    let expected = fs::read_to_string(&path).ok();
+    if expected.as_deref() == Some(&actual) {
+        let _ = fs::remove_file(&new);
+        return;
+    }
+    let write = |path: &Path| {
+        fs::create_dir_all(&dir)
+            .and_then(|_| fs::write(path, &actual))
+            .unwrap_or_else(|err| panic!("failed to write `{}`: {err}", path.display()))
+    };
+    if bless {
+        write(&path);
+        let _ = fs::remove_file(&new);
+        return;
+    }
+    write(&new);
+    match expected {
+        Some(expected) => {
+            let lines = |text: &str| text.lines().map(String::from).collect::<Vec<_>>();
+            panic!(
+                "snapshot `{name}` differs, the output was written to `{}`\n\
+                 run with QUOTE2_BLESS=1 to update it\n{}",
+                new.display(),
+                diff("snapshot", &lines(&expected), "actual", &lines(&actual))
+            );
+        }
+        None => panic!(
+            "snapshot `{name}` does not exist, the output was written to `{}`\n\
+             run with QUOTE2_BLESS=1 to create it",
+            new.display()
+        ),
+    }
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_eq(actual: &impl quote::ToTokens, expected: &impl quote::ToTokens, spans: bool) {
+    let actual = actual.to_token_stream();
+    let expected = expected.to_token_stream();
+    if let Some(mismatch) = first_mismatch(&actual, &expected, spans, &mut Vec::new()) {
+        panic!(
+            "assertion `left == right` failed: token streams differ\n{mismatch}\n{}",
+            diff("left", &lines(&actual), "right", &lines(&expected))
+        );
+    }
+}
+
+fn first_mismatch(
+    left: &TokenStream,
+    right: &TokenStream,
+    spans: bool,
+    path: &mut Vec<String>,
+) -> Option<String> {
+    let left = flatten(left);
+    let right = flatten(right);
+    for i in 0..left.len().max(right.len()) {
+        let (l, r) = (left.get(i), right.get(i));
+        let same = match (l, r) {
+            (Some(TokenTree::Group(a)), Some(TokenTree::Group(b)))
+                if a.delimiter() == b.delimiter() =>
+            {
+                path.push(format!("{} (token {i})", group_name(a.delimiter())));
+                let inner = first_mismatch(&a.stream(), &b.stream(), spans, path);
+                if inner.is_some() {
+                    return inner;
+                }
+                path.pop();
+                !spans || span_eq(l, r)
+            }
+            (Some(TokenTree::Ident(a)), Some(TokenTree::Ident(b))) => a == b,
+            (Some(TokenTree::Punct(a)), Some(TokenTree::Punct(b))) => a.as_char() == b.as_char(),
+            (Some(TokenTree::Literal(a)), Some(TokenTree::Literal(b))) => {
+                a.to_string() == b.to_string()
+            }
+            _ => false,
+        };
+        if !same || (spans && !span_eq(l, r)) {
+            let describe = |tt: Option<&TokenTree>| match tt {
+                Some(tt) if spans => format!("`{}` at {:?}", short(tt), tt.span()),
+                Some(tt) => format!("`{}`", short(tt)),
+                None => "end of group".into(),
+            };
+            let mut at = String::from("root");
+            for group in path.iter() {
+                at.push_str(" > ");
+                at.push_str(group);
+            }
+            return Some(format!(
+                "first difference at token {i} in {at}\n  left: {}\n right: {}",
+                describe(l),
+                describe(r)
+            ));
+        }
+    }
+    None
+}
+
+fn span_eq(l: Option<&TokenTree>, r: Option<&TokenTree>) -> bool {
+    match (l, r) {
+        (Some(l), Some(r)) => format!("{:?}", l.span()) == format!("{:?}", r.span()),
+        _ => false,
+    }
+}
+
+fn group_name(delimiter: Delimiter) -> &'static str {
+    match delimiter {
+        Delimiter::Parenthesis => "( ... )",
+        Delimiter::Brace => "{ ... }",
+        Delimiter::Bracket => "[ ... ]",
+        Delimiter::None => "...",
+    }
+}
+
+/// A token, with the content of groups elided.
+fn short(tt: &TokenTree) -> String {
+    match tt {
+        TokenTree::Group(g) => group_name(g.delimiter()).into(),
+        tt => tt.to_string(),
+    }
+}
+
+fn lines(stream: &TokenStream) -> Vec<String> {
+    print(stream).lines().map(String::from).collect()
+}
+
+/// A line diff of `left` against `right` (longest common subsequence).
+fn diff(left_name: &str, left: &[String], right_name: &str, right: &[String]) -> String {
+    let (n, m) = (left.len(), right.len());
+    let mut lcs = alloc::vec![alloc::vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if left[i] == right[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut out = format!("--- {left_name}\n+++ {right_name}\n");
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && left[i] == right[j] {
+            out.push_str(&format!("  {}\n", left[i]));
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("- {}\n", left[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+ {}\n", right[j]));
+            j += 1;
+        }
+    }
+    out
+}
@@ -1,70 +1,274 @@
+//! Runtime helpers for the code generated by `quote!`.
+//!
+//! They are generic over the [`Token`] type of the target, so the same
+//! generated code can build `proc_macro2` or (with the `proc-macro-native`
+//! feature) `proc_macro` tokens. Spans are always `proc_macro2::Span`s.
+use crate::Quote;
+use core::ffi::CStr;
+use core::marker::PhantomData;
+use core::str::FromStr;
 use proc_macro2::*;
-use std::str::FromStr;
 
-fn ident_maybe_raw(id: &str, span: Span) -> Ident {
-    if let Some(id) = id.strip_prefix("r#") {
-        Ident::new_raw(id, span)
-    } else {
-        Ident::new(id, span)
+/// A token tree type that `quote!` can build.
+pub trait Token: Sized {
+    /// Content of groups, written by the generated code just like the target.
+    type Stream: Quote<Tree = Self> + Default;
+    type Literal: Lit;
+
+    fn punct(ch: char, spacing: Spacing, span: Option<Span>) -> Self;
+    fn ident(name: &str, span: Span) -> Self;
+    /// `name` without its `r#` prefix.
+    fn raw_ident(name: &str, span: Span) -> Self;
+    fn lit(lit: Self::Literal, span: Option<Span>) -> Self;
+    fn parsed_lit(repr: &str, span: Option<Span>) -> Self;
+    fn group(delimiter: Delimiter, stream: Self::Stream, span: Option<Span>) -> Self;
+
+    /// Converts interpolated `ToTokens` output.
+    fn from_stream(stream: TokenStream) -> impl IntoIterator<Item = Self>;
+}
+
+/// Literal constructors, e.g. `quote2::tt::Lit::u8_suffixed(1u8)`.
+pub trait Lit: Sized {
+    fn string(s: &str) -> Self;
+    fn character(ch: char) -> Self;
+    fn byte_character(byte: u8) -> Self;
+    fn byte_string(bytes: &[u8]) -> Self;
+    fn c_string(s: &CStr) -> Self;
+    fn u8_suffixed(n: u8) -> Self;
+    fn u16_suffixed(n: u16) -> Self;
+    fn u32_suffixed(n: u32) -> Self;
+    fn u64_suffixed(n: u64) -> Self;
+    fn u128_suffixed(n: u128) -> Self;
+    fn usize_suffixed(n: usize) -> Self;
+    fn i8_suffixed(n: i8) -> Self;
+    fn i16_suffixed(n: i16) -> Self;
+    fn i32_suffixed(n: i32) -> Self;
+    fn i64_suffixed(n: i64) -> Self;
+    fn i128_suffixed(n: i128) -> Self;
+    fn isize_suffixed(n: isize) -> Self;
+    fn u128_unsuffixed(n: u128) -> Self;
+    fn f32_suffixed(n: f32) -> Self;
+    fn f64_suffixed(n: f64) -> Self;
+    fn f64_unsuffixed(n: f64) -> Self;
+}
+
+macro_rules! impl_lit {
+    ($ty:ty) => {
+        impl $crate::tt::Lit for $ty {
+            impl_lit!(@fns
+                string(&str), character(char), byte_character(u8),
+                byte_string(&[u8]), c_string(&core::ffi::CStr),
+                u8_suffixed(u8), u16_suffixed(u16), u32_suffixed(u32),
+                u64_suffixed(u64), u128_suffixed(u128), usize_suffixed(usize),
+                i8_suffixed(i8), i16_suffixed(i16), i32_suffixed(i32),
+                i64_suffixed(i64), i128_suffixed(i128), isize_suffixed(isize),
+                u128_unsuffixed(u128), f32_suffixed(f32), f64_suffixed(f64),
+                f64_unsuffixed(f64)
+            );
+        }
+    };
+    (@fns $($name:ident($arg:ty)),*) => {$(
+        #[inline]
+        fn $name(v: $arg) -> Self {
+            Self::$name(v)
+        }
+    )*};
+}
+#[cfg(feature = "proc-macro-native")]
+pub(crate) use impl_lit;
+
+impl_lit!(Literal);
+
+impl Token for TokenTree {
+    type Stream = TokenStream;
+    type Literal = Literal;
+
+    fn punct(ch: char, spacing: Spacing, span: Option<Span>) -> Self {
+        let mut p = Punct::new(ch, spacing);
+        if let Some(span) = span {
+            p.set_span(span);
+        }
+        p.into()
+    }
+
+    fn ident(name: &str, span: Span) -> Self {
+        Ident::new(name, span).into()
+    }
+
+    fn raw_ident(name: &str, span: Span) -> Self {
+        Ident::new_raw(name, span).into()
+    }
+
+    fn lit(mut lit: Literal, span: Option<Span>) -> Self {
+        if let Some(span) = span {
+            lit.set_span(span);
+        }
+        lit.into()
+    }
+
+    fn parsed_lit(repr: &str, span: Option<Span>) -> Self {
+        // Not the location in `repr`, with `span-locations`.
+        let span = span.unwrap_or_else(Span::call_site);
+        Self::lit(
+            Literal::from_str(repr).expect("invalid literal"),
+            Some(span),
+        )
+    }
+
+    fn group(delimiter: Delimiter, stream: TokenStream, span: Option<Span>) -> Self {
+        let mut g = Group::new(delimiter, stream);
+        if let Some(span) = span {
+            g.set_span(span);
+        }
+        g.into()
+    }
+
+    #[inline]
+    fn from_stream(stream: TokenStream) -> impl IntoIterator<Item = Self> {
+        stream
     }
 }
 
 #[inline]
-pub fn punct_join(ch: char) -> TokenTree {
-    Punct::new(ch, Spacing::Joint).into()
+pub fn punct_join<T: Token>(ch: char) -> T {
+    T::punct(ch, Spacing::Joint, None)
 }
 
-pub fn punct_join_span(span: Span, ch: char) -> TokenTree {
-    let mut t = Punct::new(ch, Spacing::Joint);
-    t.set_span(span);
-    t.into()
+#[inline]
+pub fn punct_join_span<T: Token>(span: Span, ch: char) -> T {
+    T::punct(ch, Spacing::Joint, Some(span))
+}
+
+#[inline]
+pub fn punct<T: Token>(ch: char) -> T {
+    T::punct(ch, Spacing::Alone, None)
+}
+
+#[inline]
+pub fn punct_span<T: Token>(span: Span, ch: char) -> T {
+    T::punct(ch, Spacing::Alone, Some(span))
 }
 
 #[inline]
-pub fn punct(ch: char) -> TokenTree {
-    Punct::new(ch, Spacing::Alone).into()
+pub fn ident<T: Token>(name: &str) -> T {
+    T::ident(name, Span::call_site())
 }
 
-pub fn punct_span(span: Span, ch: char) -> TokenTree {
-    let mut p = Punct::new(ch, Spacing::Alone);
-    p.set_span(span);
-    p.into()
+#[inline]
+pub fn ident_span<T: Token>(span: Span, name: &str) -> T {
+    T::ident(name, span)
 }
 
 #[inline]
-pub fn ident(name: &str) -> TokenTree {
-    ident_maybe_raw(name, Span::call_site()).into()
+pub fn raw_ident<T: Token>(name: &str) -> T {
+    T::raw_ident(name, Span::call_site())
 }
 
 #[inline]
-pub fn ident_span(span: Span, name: &str) -> TokenTree {
-    ident_maybe_raw(name, span).into()
+pub fn raw_ident_span<T: Token>(span: Span, name: &str) -> T {
+    T::raw_ident(name, span)
 }
 
+/// Builds an identifier from an arbitrary string, e.g. a field name read
+/// from a schema, without ever panicking.
+///
+/// Keywords are escaped as raw identifiers (`type` becomes `r#type`), and
+/// `self`, `Self`, `super` and `crate`, which can't be raw, get a trailing
+/// underscore. Characters that can't appear in an identifier are replaced
+/// by `_`, and a name that doesn't start like an identifier is prefixed
+/// with `_`. A leading `r#` is kept only if the rest needs it.
+///
+/// ```
+/// use quote2::ident_escaped;
+/// assert_eq!(ident_escaped("type").to_string(), "r#type");
+/// assert_eq!(ident_escaped("self").to_string(), "self_");
+/// assert_eq!(ident_escaped("content-type").to_string(), "content_type");
+/// assert_eq!(ident_escaped("2d").to_string(), "_2d");
+/// assert_eq!(ident_escaped("r#name").to_string(), "name");
+/// ```
 #[inline]
-pub fn parsed_lit(s: &str) -> TokenTree {
-    Literal::from_str(s).expect("invalid literal").into()
+pub fn ident_escaped(name: &str) -> Ident {
+    ident_escaped_span(Span::call_site(), name)
 }
 
-pub fn parsed_lit_span(span: Span, s: &str) -> TokenTree {
-    let mut l = Literal::from_str(s).expect("invalid literal");
-    l.set_span(span);
-    l.into()
+/// [`ident_escaped`] with the given span.
+pub fn ident_escaped_span(span: Span, name: &str) -> Ident {
+    let name = name.strip_prefix("r#").unwrap_or(name);
+    let mut id = alloc::string::String::with_capacity(name.len() + 1);
+    for (i, ch) in name.chars().enumerate() {
+        if i == 0 && !(ch == '_' || unicode_ident::is_xid_start(ch)) {
+            id.push('_');
+        }
+        match unicode_ident::is_xid_continue(ch) {
+            true => id.push(ch),
+            false => id.push('_'),
+        }
+    }
+    match id.as_str() {
+        "" => Ident::new("_", span),
+        "self" | "Self" | "super" | "crate" => {
+            id.push('_');
+            Ident::new(&id, span)
+        }
+        // `_` is a keyword too, but it is a valid (non raw) identifier.
+        _ if KEYWORDS.contains(&id.as_str()) => Ident::new_raw(&id, span),
+        _ => Ident::new(&id, span),
+    }
 }
 
+/// Strict and reserved keywords of every edition.
+const KEYWORDS: [&str; 48] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
 #[inline]
-pub fn group(delimiter: char, f: impl FnOnce(&mut TokenStream)) -> TokenTree {
-    _group(delimiter, f).into()
+pub fn lit<T: Token>(l: T::Literal) -> T {
+    T::lit(l, None)
 }
 
-pub fn group_span(span: Span, delimiter: char, f: impl FnOnce(&mut TokenStream)) -> TokenTree {
-    let mut g = _group(delimiter, f);
-    g.set_span(span);
-    g.into()
+#[inline]
+pub fn lit_span<T: Token>(span: Span, l: T::Literal) -> T {
+    T::lit(l, Some(span))
 }
 
-fn _group(delimiter: char, f: impl FnOnce(&mut TokenStream)) -> Group {
-    let mut stream = TokenStream::new();
+#[inline]
+pub fn parsed_lit<T: Token>(s: &str) -> T {
+    T::parsed_lit(s, None)
+}
+
+#[inline]
+pub fn parsed_lit_span<T: Token>(span: Span, s: &str) -> T {
+    T::parsed_lit(s, Some(span))
+}
+
+/// The target is only used to pick the token type of the group's content.
+#[inline]
+pub fn group<Q>(_: &Q, delimiter: char, f: impl FnOnce(&mut <Q::Tree as Token>::Stream)) -> Q::Tree
+where
+    Q: Quote + ?Sized,
+{
+    _group::<Q::Tree>(delimiter, f, None)
+}
+
+#[inline]
+pub fn group_span<Q>(
+    _: &Q,
+    span: Span,
+    delimiter: char,
+    f: impl FnOnce(&mut <Q::Tree as Token>::Stream),
+) -> Q::Tree
+where
+    Q: Quote + ?Sized,
+{
+    _group::<Q::Tree>(delimiter, f, Some(span))
+}
+
+fn _group<T: Token>(delimiter: char, f: impl FnOnce(&mut T::Stream), span: Option<Span>) -> T {
+    let mut stream = T::Stream::default();
     f(&mut stream);
     let delimiter = match delimiter {
         '{' => Delimiter::Brace,
@@ -72,5 +276,159 @@ fn _group(delimiter: char, f: impl FnOnce(&mut TokenStream)) -> Group {
         '(' => Delimiter::Parenthesis,
         _ => Delimiter::None,
     };
-    Group::new(delimiter, stream)
+    T::group(delimiter, stream, span)
+}
+
+/// Where the unspanned tokens of a template get their span from, see [`Site`].
+pub trait SpanSource {
+    fn span() -> Span;
+}
+
+/// [`Span::call_site()`], what the free functions of this module use.
+pub struct CallSite;
+
+impl SpanSource for CallSite {
+    #[inline]
+    fn span() -> Span {
+        Span::call_site()
+    }
+}
+
+/// [`Span::mixed_site()`], used by `quote_mixed!`.
+pub struct MixedSite;
+
+impl SpanSource for MixedSite {
+    #[inline]
+    fn span() -> Span {
+        Span::mixed_site()
+    }
+}
+
+/// The token helpers of this module, with every token spanned by `S`.
+///
+/// `quote_mixed!` writes `quote2::tt::Mixed::ident("x")` where `quote!`
+/// writes `quote2::tt::ident("x")`.
+pub struct Site<S>(PhantomData<S>);
+
+/// The helpers of `quote_mixed!`.
+pub type Mixed = Site<MixedSite>;
+
+impl<S: SpanSource> Site<S> {
+    #[inline]
+    pub fn punct_join<T: Token>(ch: char) -> T {
+        T::punct(ch, Spacing::Joint, Some(S::span()))
+    }
+
+    #[inline]
+    pub fn punct<T: Token>(ch: char) -> T {
+        T::punct(ch, Spacing::Alone, Some(S::span()))
+    }
+
+    #[inline]
+    pub fn ident<T: Token>(name: &str) -> T {
+        T::ident(name, S::span())
+    }
+
+    #[inline]
+    pub fn raw_ident<T: Token>(name: &str) -> T {
+        T::raw_ident(name, S::span())
+    }
+
+    #[inline]
+    pub fn lit<T: Token>(l: T::Literal) -> T {
+        T::lit(l, Some(S::span()))
+    }
+
+    #[inline]
+    pub fn parsed_lit<T: Token>(s: &str) -> T {
+        T::parsed_lit(s, Some(S::span()))
+    }
+
+    #[inline]
+    pub fn group<Q>(
+        _: &Q,
+        delimiter: char,
+        f: impl FnOnce(&mut <Q::Tree as Token>::Stream),
+    ) -> Q::Tree
+    where
+        Q: Quote + ?Sized,
+    {
+        _group::<Q::Tree>(delimiter, f, Some(S::span()))
+    }
+}
+
+/// `#x @ span`: interpolates `x` with every token (recursively) set to `span`.
+pub struct Respan<'a, T: ?Sized>(pub &'a T, pub Span);
+
+impl<T: quote::ToTokens + ?Sized> quote::ToTokens for Respan<'_, T> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(respan(self.0.to_token_stream(), self.1));
+    }
+}
+
+fn respan(stream: TokenStream, span: Span) -> TokenStream {
+    stream
+        .into_iter()
+        .map(|mut tt| {
+            if let TokenTree::Group(g) = &tt {
+                let mut group = Group::new(g.delimiter(), respan(g.stream(), span));
+                group.set_span(span);
+                tt = group.into();
+            } else {
+                tt.set_span(span);
+            }
+            tt
+        })
+        .collect()
+}
+
+/// A long run of fixed tokens, written by `quote!` as a single string.
+pub fn parse<Q>(_: &Q, src: &str) -> impl IntoIterator<Item = Q::Tree>
+where
+    Q: Quote + ?Sized,
+{
+    <Q::Tree as Token>::from_stream(parse_static(src))
+}
+
+/// Parses a run of fixed tokens, spanned with `Span::call_site()` like the
+/// tokens built one by one. Inside a proc macro, parsing already does that.
+fn parse_static(src: &str) -> TokenStream {
+    let stream = TokenStream::from_str(src).expect("invalid static chunk");
+    #[cfg(feature = "std")]
+    if proc_macro::is_available() {
+        return stream;
+    }
+    respan(stream, Span::call_site())
+}
+
+#[cfg(feature = "std")]
+extern crate proc_macro;
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// `quote_cached!` runs, by the address and length of their source.
+    static CACHE: core::cell::RefCell<std::collections::HashMap<(usize, usize), TokenStream>> =
+        Default::default();
+}
+
+/// A run of fixed tokens in `quote_cached!`, parsed once per thread and
+/// cloned, except inside a proc macro.
+pub fn cached<Q>(_: &Q, src: &'static str) -> impl IntoIterator<Item = Q::Tree>
+where
+    Q: Quote + ?Sized,
+{
+    #[cfg(feature = "std")]
+    let stream = if proc_macro::is_available() {
+        parse_static(src)
+    } else {
+        CACHE.with(|cache| {
+            let key = (src.as_ptr() as usize, src.len());
+            let mut cache = cache.borrow_mut();
+            let stream = cache.entry(key).or_insert_with(|| parse_static(src));
+            stream.clone()
+        })
+    };
+    #[cfg(not(feature = "std"))]
+    let stream = parse_static(src);
+    <Q::Tree as Token>::from_stream(stream)
 }
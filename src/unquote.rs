@@ -0,0 +1,113 @@
+//! Runtime support for `unquote!`.
+//!
+//! The template is lowered to a sequence of calls on a [`Cursor`], one per
+//! template token. `#var` captures input tokens up to whatever the template
+//! expects next, see [`Stop`].
+use crate::parse::{Cursor, Result};
+use crate::Error;
+use alloc::string::ToString;
+use proc_macro2::{Delimiter, Group, Ident, Literal, Punct, Span, TokenStream, TokenTree};
+use quote::ToTokens;
+
+#[inline]
+pub fn cursor(input: &impl ToTokens) -> Cursor {
+    Cursor::new(input.to_token_stream())
+}
+
+/// A literal of the template, compared by its textual representation.
+pub fn lit(cursor: &mut Cursor, repr: &str) -> Result<()> {
+    match cursor.peek() {
+        Some(TokenTree::Literal(l)) if l.to_string() == repr => {
+            cursor.next();
+            Ok(())
+        }
+        Some(tt) => Err(Error::new(tt.span(), format_args!("expected `{repr}`"))),
+        None => Err(cursor.error(format_args!("unexpected end of input, expected `{repr}`"))),
+    }
+}
+
+/// What follows a `#var` in the template.
+pub enum Stop<'a> {
+    /// The end of the enclosing group.
+    End,
+    /// Another `#var`: the binding is a single token tree.
+    One,
+    Ident(&'a str),
+    Punct(char),
+    Lit(&'a str),
+    Group(Delimiter),
+}
+
+impl Stop<'_> {
+    fn matches(&self, tt: &TokenTree) -> bool {
+        match (self, tt) {
+            (Stop::Ident(s), TokenTree::Ident(i)) => i == s,
+            (Stop::Punct(ch), TokenTree::Punct(p)) => p.as_char() == *ch,
+            (Stop::Lit(s), TokenTree::Literal(l)) => l.to_string() == *s,
+            (Stop::Group(d), TokenTree::Group(g)) => g.delimiter() == *d,
+            _ => false,
+        }
+    }
+}
+
+/// Captures the tokens of a `#var`, up to the `stop` token.
+pub fn bind<T: FromTokens>(cursor: &mut Cursor, stop: Stop) -> Result<T> {
+    let span = cursor.span();
+    let mut tokens = TokenStream::new();
+    match stop {
+        Stop::End => tokens = cursor.rest(),
+        Stop::One => tokens.extend(cursor.next()),
+        stop => {
+            while let Some(tt) = cursor.peek() {
+                if stop.matches(tt) {
+                    break;
+                }
+                tokens.extend(cursor.next());
+            }
+        }
+    }
+    T::from_tokens(tokens, span)
+}
+
+/// Types that a `#var` of an `unquote!` template can be bound to.
+pub trait FromTokens: Sized {
+    /// `span` is the first captured token, or the end of the group if nothing
+    /// was captured.
+    fn from_tokens(tokens: TokenStream, span: Span) -> Result<Self>;
+}
+
+impl FromTokens for TokenStream {
+    fn from_tokens(tokens: TokenStream, _: Span) -> Result<Self> {
+        Ok(tokens)
+    }
+}
+
+impl FromTokens for TokenTree {
+    fn from_tokens(tokens: TokenStream, span: Span) -> Result<Self> {
+        let mut cursor = Cursor::new(tokens);
+        match cursor.next() {
+            Some(tt) => cursor.end().map(|_| tt),
+            None => Err(Error::new(span, "expected a token")),
+        }
+    }
+}
+
+macro_rules! from_token_tree {
+    [$($ty:ident => $expected:literal),*] => {$(
+        impl FromTokens for $ty {
+            fn from_tokens(tokens: TokenStream, span: Span) -> Result<Self> {
+                match TokenTree::from_tokens(tokens, span)? {
+                    TokenTree::$ty(v) => Ok(v),
+                    tt => Err(Error::new(tt.span(), $expected)),
+                }
+            }
+        }
+    )*};
+}
+
+from_token_tree! {
+    Ident => "expected an identifier",
+    Punct => "expected a punctuation",
+    Literal => "expected a literal",
+    Group => "expected a group"
+}
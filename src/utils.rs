@@ -1,5 +1,7 @@
-use crate::QuoteFn;
-use proc_macro2::TokenStream;
+use crate::{QuoteFn, QuoteFnOnce};
+use core::fmt;
+use proc_macro2::{Punct, Spacing, TokenStream};
+use quote::{ToTokens, TokenStreamExt};
 
 pub fn quote_rep<'a, I, T>(
     iter: I,
@@ -15,3 +17,206 @@ where
         }
     })
 }
+
+/// Like [`quote_rep`], but consumes `iter`, so it doesn't need to be `Clone`
+/// (e.g. `vec.drain(..)`). The result can only be interpolated once.
+pub fn quote_rep_once<'a, I, T>(
+    iter: I,
+    mut f: impl FnMut(&mut TokenStream, T) + 'a,
+) -> QuoteFnOnce<impl FnOnce(&mut TokenStream) + 'a>
+where
+    I: IntoIterator<Item = T> + 'a,
+{
+    crate::quote_once(move |t| {
+        for val in iter {
+            f(t, val);
+        }
+    })
+}
+
+/// Written between the items of [`quote_sep`] and friends.
+///
+/// A `char` is a single punct (`','`), a `&str` an operator made of joint
+/// puncts (`"=>"`), and [`Sep`] wraps any other `ToTokens` value.
+///
+/// # Panics
+///
+/// A `char` or `&str` separator panics when it is written if it contains
+/// anything but punctuation, e.g. `"and"` or `" "`. Use
+/// `Sep(format_ident!("and"))` for other tokens.
+pub trait Separator {
+    fn write_sep(&self, tokens: &mut TokenStream);
+}
+
+/// The chars `Punct::new` accepts.
+const PUNCT_CHARS: &str = "=<>!~+-*/%^&|@.,;:#$?'";
+
+fn sep_punct(ch: char, spacing: Spacing, sep: &dyn fmt::Debug) -> Punct {
+    assert!(
+        PUNCT_CHARS.contains(ch),
+        "separator {sep:?} contains {ch:?}, which is not a punctuation char, \
+         use `quote2::utils::Sep` for other tokens"
+    );
+    Punct::new(ch, spacing)
+}
+
+impl Separator for char {
+    fn write_sep(&self, tokens: &mut TokenStream) {
+        tokens.append(sep_punct(*self, Spacing::Alone, self));
+    }
+}
+
+impl Separator for &str {
+    fn write_sep(&self, tokens: &mut TokenStream) {
+        let mut chars = self.chars().peekable();
+        while let Some(ch) = chars.next() {
+            let spacing = match chars.peek() {
+                Some(_) => Spacing::Joint,
+                None => Spacing::Alone,
+            };
+            tokens.append(sep_punct(ch, spacing, self));
+        }
+    }
+}
+
+/// A `ToTokens` separator, e.g. `Sep(quote2::format_ident!("and"))`.
+#[derive(Debug, Clone, Copy)]
+pub struct Sep<T>(pub T);
+
+impl<T: ToTokens> Separator for Sep<T> {
+    fn write_sep(&self, tokens: &mut TokenStream) {
+        self.0.to_tokens(tokens);
+    }
+}
+
+fn sep_each<T>(
+    t: &mut TokenStream,
+    iter: impl IntoIterator<Item = T>,
+    sep: &impl Separator,
+    trailing: bool,
+    mut f: impl FnMut(&mut TokenStream, T),
+) {
+    let mut iter = iter.into_iter().peekable();
+    while let Some(val) = iter.next() {
+        f(t, val);
+        if trailing || iter.peek().is_some() {
+            sep.write_sep(t);
+        }
+    }
+}
+
+/// Like [`quote_rep`], with `sep` between the items.
+///
+/// ```
+/// use quote2::{proc_macro2::TokenStream, quote, utils::quote_sep};
+/// let args = quote_sep(["a", "b"], ',', |t, name| {
+///     let name = quote2::format_ident!("{name}");
+///     quote!(t, { #name: u8 });
+/// });
+/// let mut tokens = TokenStream::new();
+/// quote!(tokens, { fn f(#args) {} });
+/// assert_eq!(tokens.to_string(), "fn f (a : u8 , b : u8) { }");
+/// ```
+pub fn quote_sep<'a, I, T>(
+    iter: I,
+    sep: impl Separator + 'a,
+    f: impl Fn(&mut TokenStream, T) + 'a,
+) -> QuoteFn<impl Fn(&mut TokenStream) + 'a>
+where
+    I: IntoIterator<Item = T> + Clone + 'a,
+{
+    crate::quote(move |t| sep_each(t, iter.clone(), &sep, false, &f))
+}
+
+/// Like [`quote_sep`], with `sep` after the last item too.
+pub fn quote_sep_trailing<'a, I, T>(
+    iter: I,
+    sep: impl Separator + 'a,
+    f: impl Fn(&mut TokenStream, T) + 'a,
+) -> QuoteFn<impl Fn(&mut TokenStream) + 'a>
+where
+    I: IntoIterator<Item = T> + Clone + 'a,
+{
+    crate::quote(move |t| sep_each(t, iter.clone(), &sep, true, &f))
+}
+
+/// Like [`quote_sep`], with the index of each item.
+pub fn quote_sep_enumerate<'a, I, T>(
+    iter: I,
+    sep: impl Separator + 'a,
+    f: impl Fn(&mut TokenStream, usize, T) + 'a,
+) -> QuoteFn<impl Fn(&mut TokenStream) + 'a>
+where
+    I: IntoIterator<Item = T> + Clone + 'a,
+{
+    crate::quote(move |t| {
+        sep_each(
+            t,
+            iter.clone().into_iter().enumerate(),
+            &sep,
+            false,
+            |t, (i, val)| f(t, i, val),
+        )
+    })
+}
+
+/// Like [`quote_sep`], over the items of `a` and `b` in lockstep.
+///
+/// ```
+/// use quote2::{format_ident, proc_macro2::TokenStream, quote, utils::quote_sep_zip};
+/// let names = [format_ident!("x"), format_ident!("y")];
+/// let tys = [format_ident!("i32"), format_ident!("f64")];
+/// let fields = quote_sep_zip(&names, &tys, ',', |t, name, ty| {
+///     quote!(t, { #name: #ty });
+/// });
+/// let mut tokens = TokenStream::new();
+/// quote!(tokens, { struct Point { #fields } });
+/// assert_eq!(tokens.to_string(), "struct Point { x : i32 , y : f64 }");
+/// ```
+pub fn quote_sep_zip<'a, A, B, X, Y>(
+    a: A,
+    b: B,
+    sep: impl Separator + 'a,
+    f: impl Fn(&mut TokenStream, X, Y) + 'a,
+) -> QuoteFn<impl Fn(&mut TokenStream) + 'a>
+where
+    A: IntoIterator<Item = X> + Clone + 'a,
+    B: IntoIterator<Item = Y> + Clone + 'a,
+{
+    crate::quote(move |t| {
+        let items = a.clone().into_iter().zip(b.clone());
+        sep_each(t, items, &sep, false, |t, (x, y)| f(t, x, y))
+    })
+}
+
+/// Interpolates every item, with `sep` between them.
+///
+/// ```
+/// use quote2::{proc_macro2::TokenStream, quote, utils::quote_join};
+/// let bounds = quote_join(["Clone", "Send"].map(|b| quote2::format_ident!("{b}")), '+');
+/// let mut tokens = TokenStream::new();
+/// quote!(tokens, { T: #bounds });
+/// assert_eq!(tokens.to_string(), "T : Clone + Send");
+/// ```
+pub fn quote_join<'a, I>(
+    iter: I,
+    sep: impl Separator + 'a,
+) -> QuoteFn<impl Fn(&mut TokenStream) + 'a>
+where
+    I: IntoIterator + Clone + 'a,
+    I::Item: ToTokens,
+{
+    crate::quote(move |t| sep_each(t, iter.clone(), &sep, false, |t, v| v.to_tokens(t)))
+}
+
+/// Like [`quote_join`], with `sep` after the last item too.
+pub fn quote_join_trailing<'a, I>(
+    iter: I,
+    sep: impl Separator + 'a,
+) -> QuoteFn<impl Fn(&mut TokenStream) + 'a>
+where
+    I: IntoIterator + Clone + 'a,
+    I::Item: ToTokens,
+{
+    crate::quote(move |t| sep_each(t, iter.clone(), &sep, true, |t, v| v.to_tokens(t)))
+}
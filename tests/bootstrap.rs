@@ -1,27 +1,3 @@
-// use quote::ToTokens;
-
-#[macro_export]
-macro_rules! quote {
-    ($($tts: tt)*) => {{
-        let mut _o = TokenStream::new();
-        quote2::quote!(_o, {
-            $($tts)*
-        });
-        _o
-    }};
-}
-
-#[macro_export]
-macro_rules! quote_spanned {
-    ($span:tt => $($tts: tt)*) => {{
-        let mut g = TokenStream::new();
-        quote2::quote_spanned!($span, g, {
-            $($tts)*
-        });
-        g
-    }};
-}
-
 pub struct QuoteIter<T>(pub T);
 
 impl<T> From<T> for QuoteIter<T> {
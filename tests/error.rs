@@ -0,0 +1,49 @@
+use proc_macro2::Span;
+use quote2::{Error, ToTokens};
+use std::fmt::{self, Write};
+
+#[test]
+fn test_error_to_tokens() {
+    let err = Error::new(Span::call_site(), "expected `,`");
+    assert_eq!(
+        err.to_compile_error().to_string(),
+        ":: core :: compile_error ! { \"expected `,`\" }"
+    );
+    assert_eq!(err.to_string(), "expected `,`");
+}
+
+#[test]
+fn test_error_combine() {
+    let mut err = Error::new(Span::call_site(), "first")
+        .note("while parsing `#[attr]`")
+        .help("remove it");
+    err.extend([
+        Error::new(Span::call_site(), "second"),
+        Error::new(Span::call_site(), "third").note("noted"),
+    ]);
+    assert_eq!(
+        err.to_string(),
+        "first\n= note: while parsing `#[attr]`\n= help: remove it\nsecond\nthird\n= note: noted"
+    );
+    assert_eq!(err.to_token_stream().into_iter().count(), 3 * 8);
+}
+
+#[test]
+fn test_error_fmt() {
+    struct Fails;
+    impl fmt::Display for Fails {
+        fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+            Err(fmt::Error)
+        }
+    }
+    fn render() -> Result<String, Error> {
+        let mut out = String::new();
+        write!(out, "{}", Fails)?;
+        Ok(out)
+    }
+    let err = render().unwrap_err();
+    assert_eq!(err.to_string(), fmt::Error.to_string());
+
+    let err: fmt::Error = Error::new(Span::call_site(), "oops").into();
+    assert_eq!(err, fmt::Error);
+}
@@ -0,0 +1,16 @@
+#![allow(clippy::shadow_unrelated)]
+use quote2_test_macros::{call_site_local, mixed_copy, mixed_local};
+
+#[test]
+fn test_quote_mixed_hygiene() {
+    let value = 0;
+    mixed_local!();
+    assert_eq!(value, 0);
+
+    let __o = 2;
+    mixed_copy!(value);
+    assert_eq!(__o, 2);
+
+    call_site_local!();
+    assert_eq!(value, 1);
+}
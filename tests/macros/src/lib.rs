@@ -0,0 +1,36 @@
+//! Proc macros for the tests of `quote2` that only a real macro expansion
+//! can check, such as hygiene.
+use proc_macro::TokenStream;
+use quote2::{quote, quote_mixed};
+
+/// `let value = 1;` with `quote_mixed!`, invisible to the caller.
+#[proc_macro]
+pub fn mixed_local(_: TokenStream) -> TokenStream {
+    let mut tokens = proc_macro2::TokenStream::new();
+    quote_mixed!(tokens, {
+        let value = 1;
+    });
+    tokens.into()
+}
+
+/// `let value = 1;` with `quote!`, which shadows the caller's `value`.
+#[proc_macro]
+pub fn call_site_local(_: TokenStream) -> TokenStream {
+    let mut tokens = proc_macro2::TokenStream::new();
+    quote!(tokens, {
+        let value = 1;
+    });
+    tokens.into()
+}
+
+/// `let __o = #input;` with `quote_mixed!`: the interpolated tokens keep their
+/// own span, so they resolve at the call site, while `__o` stays invisible.
+#[proc_macro]
+pub fn mixed_copy(input: TokenStream) -> TokenStream {
+    let value = proc_macro2::TokenStream::from(input);
+    let mut tokens = proc_macro2::TokenStream::new();
+    quote_mixed!(tokens, {
+        let __o = #value;
+    });
+    tokens.into()
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "proc-macro-native")]
+extern crate proc_macro;
+
+use proc_macro2::Span;
+use quote2::native::{expand, is_available, Template};
+use quote2::{quote, quote_spanned, Quote};
+
+struct Getter {
+    name: &'static str,
+    ty: &'static str,
+}
+
+impl Template for Getter {
+    fn quote<Q: Quote>(&self, tokens: &mut Q) {
+        let name = quote2::format_ident!("{}", self.name);
+        let ty = quote2::format_ident!("{}", self.ty);
+        quote!(tokens, {
+            pub fn #name(&self) -> &#ty {
+                &self.#name
+            }
+        });
+    }
+}
+
+#[test]
+fn test_fallback() {
+    assert!(!is_available());
+    let getter = Getter {
+        name: "id",
+        ty: "u64",
+    };
+    assert_eq!(
+        expand(&getter).to_string(),
+        "pub fn id (& self) -> &u64 { & self .id }"
+    );
+}
+
+// Only type checked, `proc_macro` tokens can't be built outside a proc-macro.
+#[allow(dead_code)]
+fn native(tokens: &mut proc_macro::TokenStream, span: Span) {
+    let items = [1u8, 2];
+    quote_spanned!(span, tokens, {
+        const ITEMS: [u8; 2] = [#(#items),*];
+        let s = ("str", 'c', b'b', b"bytes", c"c", 1.5, 1_f32, r"raw");
+    });
+    quote!(tokens, {
+        #if items.is_empty() { () } #else { [] }
+    });
+    Getter { name: "a", ty: "b" }.quote(tokens);
+}
@@ -0,0 +1,44 @@
+//! Checks that the core API doesn't use `std` itself, run it with
+//! `cargo test --no-default-features --test no_std`. `proc-macro2` still
+//! needs `std`, so this runs on a host target.
+#![no_std]
+extern crate alloc;
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote2::{quote, quote_spanned, utils::quote_rep, Quote, QuoteFn};
+
+#[test]
+fn test_quote() {
+    let fields = ["a", "b"].map(|name| quote2::proc_macro2::Ident::new(name, Span::call_site()));
+    let body: QuoteFn<_> = quote2::quote(|t| {
+        quote!(t, { 1u8 + 2 });
+    });
+    let mut tokens = TokenStream::new();
+    quote!(tokens, {
+        struct S { #(#fields: u8),* }
+        const X: u8 = #body;
+    });
+    assert_eq!(
+        tokens.to_string(),
+        "struct S { a : u8 , b : u8 } const X : u8 = 1u8 + 2 ;"
+    );
+}
+
+#[test]
+fn test_tt_and_utils() {
+    let mut trees: Vec<TokenTree> = Vec::new();
+    trees.add_trees([
+        quote2::tt::ident("x"),
+        quote2::tt::punct_join('+'),
+        quote2::tt::punct('='),
+        quote2::tt::lit(quote2::tt::Lit::u8_suffixed(1)),
+    ]);
+    let list = quote_rep(1..4, |t, n: i32| {
+        quote_spanned!(Span::call_site(), t, { #n, });
+    });
+    trees.add_tokens(&list);
+    let stream: TokenStream = trees.into_iter().collect();
+    assert_eq!(stream.to_string(), "x += 1u8 1i32 , 2i32 , 3i32 ,");
+}
@@ -0,0 +1,94 @@
+use proc_macro2::{Delimiter, TokenStream};
+use quote2::parse::Cursor;
+
+fn cursor(src: &str) -> Cursor {
+    Cursor::new(src.parse::<TokenStream>().unwrap())
+}
+
+#[test]
+fn test_cursor() {
+    let mut c = cursor("#[attr] pub struct Unit { x: u8 } 42");
+    c.punct('#').unwrap();
+    let attr = c.group(Delimiter::Bracket).unwrap();
+    assert_eq!(attr.to_string(), "[attr]");
+    assert!(c.peek_ident("pub"));
+    c.keyword("pub").unwrap();
+    c.keyword("struct").unwrap();
+    assert_eq!(c.ident().unwrap(), "Unit");
+
+    let mut body = c.enter(Delimiter::Brace).unwrap();
+    assert_eq!(body.ident().unwrap(), "x");
+    body.punct(':').unwrap();
+    assert_eq!(body.token().unwrap().to_string(), "u8");
+    assert!(body.is_empty());
+    body.end().unwrap();
+    let err = body.ident().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unexpected end of input, expected an identifier"
+    );
+
+    assert!(c.peek_lit());
+    assert_eq!(c.lit().unwrap().to_string(), "42");
+    c.end().unwrap();
+}
+
+#[test]
+fn test_cursor_ops() {
+    let mut c = cursor("a::b => c -> d : :e");
+    c.ident().unwrap();
+    assert!(c.peek_op("::"));
+    assert!(c.peek_op(":"));
+    assert!(!c.peek_op(":::"));
+    c.op("::").unwrap();
+    c.ident().unwrap();
+    // Only the spacing *within* an operator is checked.
+    assert!(c.peek_op("="));
+    assert!(!c.peek_op("->"));
+    c.op("=>").unwrap();
+    c.ident().unwrap();
+    c.op("->").unwrap();
+    c.ident().unwrap();
+    // `: :` is not `::`.
+    assert!(!c.peek_op("::"));
+    assert_eq!(c.op("::").unwrap_err().to_string(), "expected `::`");
+    c.punct(':').unwrap();
+    c.punct(':').unwrap();
+    assert_eq!(c.rest().to_string(), "e");
+}
+
+#[test]
+fn test_cursor_errors() {
+    let mut c = cursor("struct X; extra");
+    assert_eq!(
+        c.keyword("enum").unwrap_err().to_string(),
+        "expected `enum`"
+    );
+    assert_eq!(c.lit().unwrap_err().to_string(), "expected a literal");
+    assert_eq!(
+        c.group(Delimiter::Parenthesis).unwrap_err().to_string(),
+        "expected `( ... )`"
+    );
+    c.keyword("struct").unwrap();
+    c.ident().unwrap();
+    c.punct(';').unwrap();
+    assert_eq!(c.end().unwrap_err().to_string(), "unexpected token `extra`");
+
+    let mut lookahead = c.lookahead();
+    assert!(!lookahead.peek_ident("fn"));
+    assert!(!lookahead.peek_op("->"));
+    assert!(!lookahead.peek_lit());
+    assert_eq!(
+        lookahead.error().to_string(),
+        "expected one of `fn`, `->`, or a literal"
+    );
+}
+
+#[test]
+fn test_cursor_fork() {
+    let mut c = cursor("a b");
+    let mut fork = c.clone();
+    fork.ident().unwrap();
+    assert_eq!(c.ident().unwrap(), "a");
+    assert_eq!(c.collect::<TokenStream>().to_string(), "b");
+}
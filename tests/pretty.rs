@@ -0,0 +1,96 @@
+use proc_macro2::{Delimiter, Group, Literal, TokenStream, TokenTree};
+use quote2::pretty::print;
+use quote2::quote;
+use quote2::testing::assert_tokens_eq;
+
+const SOURCE: &str = r#"
+    /// Docs
+    #[derive(Debug, Clone)]
+    pub(crate) struct Point<'a, T: Copy + 'a> { x: &'a T, y: Vec<Option<T>> }
+
+    #![allow(unused)]
+    use std::{fmt, io::*};
+
+    impl<'a, T> fmt::Display for Point<'a, T> where T: Copy + fmt::Debug, {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let v = Vec::<u8>::with_capacity(4 * 2);
+            let t = (1, 2).0.max(-1) as usize;
+            let ok = !self.y.is_empty() && t != 0 || *self.x == *self.x;
+            for i in 0..=10 { if i % 2 == 0 { continue; } else if i > 5 { break } }
+            let add = |a: u8, b| a + b;
+            let unit = move || {};
+            match self.y.first() {
+                Some(Some(v)) => { write!(f, "{:?}", v)?; }
+                Some(None) | None => {}
+            }
+            let s = S { a: 1, b: [0u8; 4] }.a;
+            x.0.1;
+            loop {}
+        }
+    }
+"#;
+
+#[test]
+fn test_print() {
+    let tokens: TokenStream = SOURCE.parse().unwrap();
+    assert_eq!(
+        print(&tokens),
+        r#"#[doc = " Docs"]
+#[derive(Debug, Clone)]
+pub(crate) struct Point<'a, T: Copy + 'a> {
+    x: &'a T,
+    y: Vec<Option<T>>
+}
+#![allow(unused)]
+use std::{fmt, io::*};
+impl<'a, T> fmt::Display for Point<'a, T> where T: Copy + fmt::Debug, {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = Vec::<u8>::with_capacity(4 * 2);
+        let t = (1, 2).0.max(-1) as usize;
+        let ok = !self.y.is_empty() && t != 0 || *self.x == *self.x;
+        for i in 0..=10 {
+            if i % 2 == 0 {
+                continue;
+            } else if i > 5 {
+                break
+            }
+        }
+        let add = |a: u8, b| a + b;
+        let unit = move || {};
+        match self.y.first() {
+            Some(Some(v)) => {
+                write!(f, "{:?}", v)?;
+            }
+            Some(None) | None => {}
+        }
+        let s = S {
+            a: 1,
+            b: [0u8; 4]
+        }.a;
+        x.0.1;
+        loop {}
+    }
+}
+"#
+    );
+}
+
+#[test]
+fn test_round_trip() {
+    let tokens: TokenStream = SOURCE.parse().unwrap();
+    let printed: TokenStream = print(&tokens).parse().unwrap();
+    assert_tokens_eq!(printed, tokens);
+
+    // `x.0.1` built from two integers, and an invisible group.
+    let index = |n| TokenTree::Literal(Literal::usize_unsuffixed(n));
+    let inner = TokenStream::from_iter([index(1)]);
+    let group = Group::new(Delimiter::None, inner);
+    let mut tokens = TokenStream::new();
+    quote!(tokens, {
+        /// doc
+        fn f() { x.#{index(0)}.#group; }
+    });
+    let printed = print(&tokens);
+    assert_eq!(printed, "#[doc = \" doc\"]\nfn f() {\n    x.0 .1;\n}\n");
+    assert_tokens_eq!(printed.parse::<TokenStream>().unwrap(), tokens);
+}
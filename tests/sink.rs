@@ -0,0 +1,60 @@
+use proc_macro2::{TokenStream, TokenTree};
+use quote2::sink::{Extender, TokenHasher};
+use quote2::{quote, Quote};
+use std::collections::VecDeque;
+
+fn write_fn(mut out: impl Quote, name: &str) {
+    let name = quote2::format_ident!("{name}");
+    quote!(out, {
+        fn #name() -> [u8; 2] { [1, 2] }
+    });
+}
+
+#[test]
+fn test_vec_sink() {
+    let mut trees: Vec<TokenTree> = Vec::new();
+    write_fn(&mut trees, "vec");
+    let stream: TokenStream = trees.into_iter().collect();
+    assert_eq!(stream.to_string(), "fn vec () -> [u8 ; 2] { [1 , 2] }");
+}
+
+#[test]
+fn test_mut_ref_sink() {
+    let mut tokens = TokenStream::new();
+    write_fn(&mut tokens, "a");
+    write_fn(&mut &mut tokens, "b");
+    assert_eq!(
+        tokens.to_string(),
+        "fn a () -> [u8 ; 2] { [1 , 2] } fn b () -> [u8 ; 2] { [1 , 2] }"
+    );
+}
+
+#[test]
+fn test_extender_sink() {
+    let mut buf = Extender(VecDeque::new());
+    write_fn(&mut buf, "deque");
+    assert_eq!(buf.len(), 7);
+    assert_eq!(buf.pop_front().unwrap().to_string(), "fn");
+}
+
+#[test]
+fn test_token_hasher() {
+    let hash = |f: &dyn Fn(&mut TokenHasher)| {
+        let mut hasher = TokenHasher::new();
+        f(&mut hasher);
+        (hasher.count(), hasher.finish())
+    };
+    let a = hash(&|t| write_fn(t, "f"));
+    assert_eq!(a, hash(&|t| write_fn(t, "f")));
+    assert_eq!(a.0, 14);
+    assert_ne!(a, hash(&|t| write_fn(t, "g")));
+
+    let grouped = hash(&|t| {
+        quote!(t, { (a) b });
+    });
+    let nested = hash(&|t| {
+        quote!(t, { (a b) });
+    });
+    assert_eq!(grouped.0, nested.0);
+    assert_ne!(grouped.1, nested.1);
+}
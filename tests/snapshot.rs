@@ -0,0 +1,76 @@
+#![cfg(feature = "std")]
+
+use proc_macro2::TokenStream;
+use quote2::testing::{snapshot, snapshot_with};
+use quote2::{format_ident, quote};
+use std::path::PathBuf;
+use std::{env, fs, panic, process};
+
+fn point(ty: &str) -> TokenStream {
+    let ty = format_ident!("{ty}");
+    let mut tokens = TokenStream::new();
+    quote!(tokens, {
+        #[derive(Debug)]
+        struct Point { x: #ty, y: #ty }
+        impl Point {
+            fn sum(&self) -> #ty { self.x + self.y }
+        }
+    });
+    tokens
+}
+
+#[test]
+fn test_snapshot() {
+    snapshot!("point", point("u8"));
+}
+
+/// A manifest dir of its own for the tests that expect a failure, which call
+/// `snapshot_with` with bless mode off, so `QUOTE2_BLESS=1 cargo test` can't
+/// turn them into successes or touch `tests/snapshots`.
+fn scratch(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("quote2-{test}-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("tests/snapshots")).unwrap();
+    dir
+}
+
+#[test]
+fn test_snapshot_mismatch() {
+    let dir = scratch("snapshot-mismatch");
+    let snapshots = dir.join("tests/snapshots");
+    let stale = quote2::pretty::print(&point("u8"));
+    fs::write(snapshots.join("point.rs"), &stale).unwrap();
+
+    let err =
+        panic::catch_unwind(|| snapshot_with(dir.to_str().unwrap(), "point", &point("u16"), false))
+            .unwrap_err();
+    let msg = err.downcast::<String>().unwrap();
+    assert!(msg.starts_with("snapshot `point` differs"));
+    assert!(msg.contains("--- snapshot\n+++ actual\n  #[derive(Debug)]\n"));
+    assert!(msg.contains("\n-     x: u8,\n"));
+    assert!(msg.contains("\n+     x: u16,\n"));
+    assert_eq!(
+        fs::read_to_string(snapshots.join("point.rs.new")).unwrap(),
+        quote2::pretty::print(&point("u16"))
+    );
+    assert_eq!(
+        fs::read_to_string(snapshots.join("point.rs")).unwrap(),
+        stale
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_snapshot_missing() {
+    let dir = scratch("snapshot-missing");
+    let err = panic::catch_unwind(|| {
+        snapshot_with(dir.to_str().unwrap(), "missing", &point("u8"), false)
+    })
+    .unwrap_err();
+    let msg = err.downcast::<String>().unwrap();
+    assert!(msg.starts_with("snapshot `missing` does not exist"));
+    assert!(msg.ends_with("run with QUOTE2_BLESS=1 to create it"));
+    assert!(dir.join("tests/snapshots/missing.rs.new").exists());
+    assert!(!dir.join("tests/snapshots/missing.rs").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
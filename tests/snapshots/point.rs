@@ -0,0 +1,10 @@
+#[derive(Debug)]
+struct Point {
+    x: u8,
+    y: u8
+}
+impl Point {
+    fn sum(&self) -> u8 {
+        self.x + self.y
+    }
+}
@@ -1,8 +1,10 @@
 #![allow(
     clippy::disallowed_names,
     clippy::let_underscore_untyped,
+    clippy::manual_c_str_literals,
     clippy::shadow_unrelated,
     clippy::unseparated_literal_suffix,
+    clippy::useless_concat,
     clippy::used_underscore_binding
 )]
 mod bootstrap;
@@ -11,8 +13,9 @@ extern crate proc_macro;
 use bootstrap::QuoteIter;
 use proc_macro2::{Delimiter, Group, Ident, Span, TokenStream};
 use quote::{format_ident, TokenStreamExt};
-use quote2::Quote;
+use quote2::compat::{quote, quote_spanned};
 use std::borrow::Cow;
+use std::collections::BTreeSet;
 use std::ffi::{CStr, CString};
 
 struct X;
@@ -81,6 +84,681 @@ fn test_iter() {
     assert_eq!("X X X X", quote!(#primes).to_string());
 }
 
+#[test]
+fn test_repetition() {
+    let primes = &[X, X, X, X];
+    assert_eq!("X X X X", quote!(#(#primes)*).to_string());
+    assert_eq!("X , X , X , X ,", quote!(#(#primes,)*).to_string());
+    assert_eq!("X , X , X , X", quote!(#(#primes),*).to_string());
+}
+
+#[test]
+fn test_fancier_repetition() {
+    let foo = vec!['a', 'b'];
+    let bar = vec![true, false];
+    let tokens = quote! {
+        #(#foo: #bar),*
+    };
+    let expected = r#"'a' : true , 'b' : false"#;
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_nested_fancy_repetition() {
+    let nested = vec![vec!['a', 'b', 'c'], vec!['x', 'y', 'z']];
+    let tokens = quote! {
+        #(
+            #(#nested)*
+        ),*
+    };
+    let expected = "'a' 'b' 'c' , 'x' 'y' 'z'";
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_duplicate_name_repetition() {
+    let foo = &["a", "b"];
+    let tokens = quote! {
+        #(#foo: #foo),*
+        #(#foo: #foo),*
+    };
+    let expected = r#""a" : "a" , "b" : "b" "a" : "a" , "b" : "b""#;
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_duplicate_name_repetition_no_copy() {
+    let foo = vec!["a".to_owned(), "b".to_owned()];
+    let tokens = quote! {
+        #(#foo: #foo),*
+    };
+    let expected = r#""a" : "a" , "b" : "b""#;
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_btreeset_repetition() {
+    let mut set = BTreeSet::new();
+    set.insert("a".to_owned());
+    set.insert("b".to_owned());
+    let tokens = quote! {
+        #(#set: #set),*
+    };
+    let expected = r#""a" : "a" , "b" : "b""#;
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_variable_name_conflict() {
+    let __i = vec!['a', 'b'];
+    let __has = vec!['c', 'd'];
+    let tokens = quote! { #(#__i #__has),* };
+    let expected = "'a' 'c' , 'b' 'd'";
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_nonrep_in_repetition() {
+    let rep = vec!["a", "b"];
+    let nonrep = "c";
+
+    let tokens = quote! {
+        #(#rep #rep : #nonrep #nonrep),*
+    };
+
+    let expected = r#""a" "a" : "c" "c" , "b" "b" : "c" "c""#;
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_star_after_repetition() {
+    let c = vec!['0', '1'];
+    let tokens = quote! {
+        #(
+            f(#c);
+        )*
+        *out = None;
+    };
+    let expected = "f ('0') ; f ('1') ; * out = None ;";
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_repetition_separator() {
+    let arms = vec![format_ident!("A"), format_ident!("B")];
+    let iter = (0..2).map(|i| i * 10);
+    let tokens = quote! {
+        #(Self::#arms => #iter)=>*
+    };
+    let expected = "Self ::A => 0i32 => Self ::B => 10i32";
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_repetition_in_group() {
+    let fields = vec![format_ident!("a"), format_ident!("b")];
+    let tys = vec![quote!(u8), quote!(Vec<u8>)];
+
+    let mut tokens = TokenStream::new();
+    quote2::quote!(tokens, {
+        struct Foo {
+            #(#fields: #tys),*
+        }
+    });
+    let expected = "struct Foo { a : u8 , b : Vec < u8 > }";
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_expr_interpolation() {
+    struct Field {
+        name: Ident,
+        ty: TokenStream,
+    }
+    impl Field {
+        fn quote(&self) -> TokenStream {
+            let mut tokens = TokenStream::new();
+            quote2::quote!(tokens, { #{self.name}: #{self.ty} });
+            tokens
+        }
+    }
+
+    let field = Field {
+        name: format_ident!("value"),
+        ty: quote!(Vec<u8>),
+    };
+    let mut tokens = TokenStream::new();
+    quote2::quote!(tokens, {
+        struct S { #{field.quote()} }
+        const LEN: usize = #{field.name.to_string().len()};
+    });
+    let expected = "struct S { value : Vec < u8 > } const LEN : usize = 5usize ;";
+    assert_eq!(expected, tokens.to_string());
+
+    // `field` is still usable, nothing was moved by the interpolations above.
+    assert_eq!(field.name, "value");
+}
+
+#[test]
+fn test_expr_interpolation_in_repetition() {
+    let names = vec![format_ident!("a"), format_ident!("b")];
+    let mut tokens = TokenStream::new();
+    quote2::quote!(tokens, {
+        #(#names: #{names.to_string().to_uppercase()}),*
+    });
+    let expected = r#"a : "A" , b : "B""#;
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_if_else() {
+    for (n, expected) in [(0, "zero"), (1, "one"), (2, "many")] {
+        let tokens = quote! {
+            #if n == 0 {
+                zero
+            } #else if n == 1 {
+                one
+            } #else {
+                many
+            }
+        };
+        assert_eq!(expected, tokens.to_string());
+    }
+
+    for (doc, expected) in [("", "struct S ;"), ("S", "# [doc = \"S\"] struct S ;")] {
+        let tokens = quote!(#if !doc.is_empty() { #[doc = #doc] } struct S;);
+        assert_eq!(expected, tokens.to_string());
+    }
+}
+
+#[test]
+fn test_for() {
+    let fields = [("a", 1u8), ("b", 2u8)];
+    let mut tokens = TokenStream::new();
+    quote2::quote!(tokens, {
+        fn f() {
+            #for (name, value) in fields {
+                let #{format_ident!("{}", name)} = #value;
+            }
+        }
+    });
+    let expected = "fn f () { let a = 1u8 ; let b = 2u8 ; }";
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_match() {
+    enum Kind {
+        Unit,
+        Tuple(usize),
+        Named(Vec<Ident>),
+    }
+
+    let quote_kind = |kind: &Kind| {
+        quote! {
+            struct S
+            #match kind {
+                Kind::Unit => { ; }
+                Kind::Tuple(n) if *n == 0 => { (); }
+                Kind::Tuple(n) => {
+                    (#for _ in 0..*n { u8, });
+                }
+                Kind::Named(fields) => {
+                    { #(#fields: u8),* }
+                },
+            }
+        }
+        .to_string()
+    };
+
+    assert_eq!("struct S ;", quote_kind(&Kind::Unit));
+    assert_eq!("struct S () ;", quote_kind(&Kind::Tuple(0)));
+    assert_eq!("struct S (u8 , u8 ,) ;", quote_kind(&Kind::Tuple(2)));
+    assert_eq!(
+        "struct S { a : u8 , b : u8 }",
+        quote_kind(&Kind::Named(vec![format_ident!("a"), format_ident!("b")]))
+    );
+}
+
+#[test]
+fn test_directive_in_repetition() {
+    // Bindings of directives are not iterated by the repetition, even if an
+    // outer variable has the same name.
+    let x = [9u8, 9u8];
+    let names = [format_ident!("a"), format_ident!("b")];
+    let values = [1u8, 2u8];
+    let opt = Some(3u8);
+    let tokens = quote! {
+        #(
+            #names: [#for x in &values { #x, }]
+            #if let Some(x) = opt { = #x }
+            #match opt { Some(v) => { (#v) } None => { () } }
+        );*
+    };
+    let expected = "a : [1u8 , 2u8 ,] = 3u8 (3u8) ; b : [1u8 , 2u8 ,] = 3u8 (3u8)";
+    assert_eq!(expected, tokens.to_string());
+    let _ = x;
+}
+
+#[test]
+fn test_target_expr() {
+    struct Gen {
+        out: TokenStream,
+        prefix: &'static str,
+    }
+    impl Gen {
+        fn unit(&mut self, name: &Ident) {
+            quote2::quote!(self.out, {
+                struct #name;
+            });
+        }
+        fn name(&self) -> Ident {
+            format_ident!("{}B", self.prefix)
+        }
+        fn unit_named(&mut self) {
+            // The target is borrowed by each write, not for the whole template.
+            quote2::quote!(self.out, {
+                struct #{self.name()} { x: #{self.prefix} }
+            });
+        }
+    }
+    fn field(tokens: &mut TokenStream) {
+        quote2::quote!(*tokens, { x: u8 });
+    }
+
+    let mut gen = Gen {
+        out: TokenStream::new(),
+        prefix: "Unit",
+    };
+    gen.unit(&format_ident!("A"));
+    field(&mut gen.out);
+    assert_eq!("struct A ; x : u8", gen.out.to_string());
+    gen.out = TokenStream::new();
+    gen.unit_named();
+    assert_eq!("struct UnitB { x : \"Unit\" }", gen.out.to_string());
+
+    let mut streams = [TokenStream::new()];
+    quote2::quote!(streams[0], { a });
+    assert_eq!("a", streams[0].to_string());
+
+    // The commas of a turbofish don't end the target.
+    fn pick<A, B>(streams: &mut [TokenStream; 2]) -> &mut TokenStream {
+        let _ = std::marker::PhantomData::<(A, B)>;
+        &mut streams[1]
+    }
+    let mut streams = [TokenStream::new(), TokenStream::new()];
+    quote2::quote!(*pick::<Vec<(u8, u16)>, fn(u8, u8) -> u8>(&mut streams), {
+        b
+    });
+    assert_eq!("b", streams[1].to_string());
+}
+
+#[test]
+fn test_span_expr() {
+    use std::cell::Cell;
+
+    let calls = Cell::new(0);
+    let span = || {
+        calls.set(calls.get() + 1);
+        Span::call_site()
+    };
+    let ident = format_ident!("field");
+
+    let mut tokens = TokenStream::new();
+    quote2::quote_spanned!(span(), tokens, {
+        let #ident: (u8, u8) = (1, 2);
+    });
+    assert_eq!("let field : (u8 , u8) = (1 , 2) ;", tokens.to_string());
+    assert_eq!(calls.get(), 1);
+
+    let mut tokens = TokenStream::new();
+    quote2::quote_spanned!(ident.span(), tokens, { #ident });
+    assert_eq!("field", tokens.to_string());
+}
+
+#[test]
+fn test_span_override() {
+    // With `span-locations`, every token parsed from a string gets its own span.
+    let spans: Vec<Span> = "first second"
+        .parse::<TokenStream>()
+        .unwrap()
+        .into_iter()
+        .map(|tt| tt.span())
+        .collect();
+    let second = spans[1];
+    let name = format_ident!("first", span = spans[0]);
+    let ty = quote!(Vec<(u8, u16)>);
+    let mut tokens = TokenStream::new();
+    quote2::quote!(tokens, {
+        let value: #ty @ second = (1, 2) @ (spans[0]);
+        #{name} @ x
+    });
+    assert_eq!(
+        tokens.to_string(),
+        "let value : Vec < (u8 , u16) > = (1 , 2) ; first @ x"
+    );
+
+    fn columns(stream: TokenStream, out: &mut Vec<usize>) {
+        for tt in stream {
+            out.push(tt.span().start().column);
+            if let proc_macro2::TokenTree::Group(g) = tt {
+                columns(g.stream(), out);
+            }
+        }
+    }
+    let trees: Vec<_> = tokens.into_iter().collect();
+    let mut ty_columns = Vec::new();
+    columns(trees[3..7].iter().cloned().collect(), &mut ty_columns);
+    assert!(ty_columns.iter().all(|&c| c == second.start().column));
+    assert_eq!(trees[8].span().start().column, spans[0].start().column);
+    assert_eq!(trees[10].span().start().column, spans[0].start().column);
+    assert_ne!(trees[0].span().start().column, second.start().column);
+}
+
+#[test]
+fn test_quote_mixed() {
+    let value = format_ident!("value");
+    let mut tokens = TokenStream::new();
+    quote2::quote_mixed!(tokens, {
+        let __o = (#value, [1, 2]);
+    });
+    assert_eq!(tokens.to_string(), "let __o = (value , [1 , 2]) ;");
+}
+
+#[test]
+fn test_span_source() {
+    use quote2::tt::{Site, SpanSource};
+    // With `span-locations`, a span parsed from a string has a location.
+    struct Second;
+    impl SpanSource for Second {
+        fn span() -> Span {
+            let tokens: TokenStream = "first second".parse().unwrap();
+            tokens.into_iter().nth(1).unwrap().span()
+        }
+    }
+    let tokens = [
+        Site::<Second>::ident("x"),
+        Site::<Second>::punct(','),
+        Site::<Second>::parsed_lit("1u8"),
+        Site::<Second>::group(&TokenStream::new(), '(', |_| {}),
+    ];
+    for tt in &tokens {
+        assert_eq!(tt.span().start().column, 6);
+    }
+    let tokens: TokenStream = tokens.into_iter().collect();
+    assert_eq!(tokens.to_string(), "x , 1u8 ()");
+}
+
+#[test]
+fn test_unquote() {
+    let input = quote! {
+        #[inline]
+        pub fn add(a: u8, b: u8) -> u8 { a + b }
+    };
+    let (attr, name, args, ret, body): (Group, Ident, TokenStream, TokenStream, TokenStream) =
+        quote2::unquote!(input, {
+            ###attr pub fn #name(#args) -> #ret { #body }
+        })
+        .unwrap();
+    assert_eq!(attr.to_string(), "[inline]");
+    assert_eq!(name.to_string(), "add");
+    assert_eq!(args.to_string(), "a : u8 , b : u8");
+    assert_eq!(ret.to_string(), "u8");
+    assert_eq!(body.to_string(), "a + b");
+
+    let (a, b): (proc_macro2::TokenTree, TokenStream) =
+        quote2::unquote!(quote!(x + y * z), { #a #b }).unwrap();
+    assert_eq!(a.to_string(), "x");
+    assert_eq!(b.to_string(), "+ y * z");
+
+    let () = quote2::unquote!(quote!(Vec<u8>), { Vec<u8> }).unwrap();
+}
+
+#[test]
+fn test_unquote_attribute() {
+    let input = quote! {
+        #[inline]
+        fn add() {}
+    };
+    let name: Ident = quote2::unquote!(input, { #[inline] fn #name() {} }).unwrap();
+    assert_eq!(name.to_string(), "add");
+
+    let input: TokenStream = "/// Adds.\n#![allow(unused)]\nfn add() {}".parse().unwrap();
+    let name: Ident = quote2::unquote!(input, {
+        /// Adds.
+        #![allow(unused)]
+        fn #name() {}
+    })
+    .unwrap();
+    assert_eq!(name.to_string(), "add");
+
+    let input = quote!(
+        #[cold]
+        fn add() {}
+    );
+    let err = quote2::unquote!(input, { #[inline] fn #name() {} })
+        .map(|_: Ident| ())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "expected `inline`");
+}
+
+#[test]
+fn test_unquote_mismatch() {
+    let input = quote!(struct Unit(u8, u16) extra);
+    let err = quote2::unquote!(input, { struct Unit { #fields } })
+        .map(|_: TokenStream| ())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "expected `{ ... }`");
+
+    let err = quote2::unquote!(input, { struct Unit(#a, #b) })
+        .map(|_: (Ident, Ident)| ())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "unexpected token `extra`");
+
+    let err = quote2::unquote!(input, { struct Unit(#a, #b, #c) extra })
+        .map(|_: (Ident, Ident, Ident)| ())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "unexpected end of input, expected `,`");
+
+    let err = quote2::unquote!(quote!(fn f() {}), { fn #name() {} })
+        .map(|_: proc_macro2::Literal| ())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "expected a literal");
+}
+
+#[test]
+fn test_move() {
+    let names = vec![format_ident!("a"), format_ident!("b")];
+    let body = quote2::quote_once(move |t| {
+        for name in names {
+            quote2::quote!(t, { let #name = 0; });
+        }
+    });
+    let fields = vec![format_ident!("x"), format_ident!("y")];
+    let mut tokens = TokenStream::new();
+    quote2::quote!(tokens, {
+        #move body
+        #(#move fields;)*
+    });
+    assert_eq!(tokens.to_string(), "let a = 0 ; let b = 0 ; x ; y ;");
+}
+
+#[test]
+#[should_panic = "`QuoteFnOnce` was interpolated more than once"]
+fn test_quote_once_twice() {
+    let body = quote2::quote_once(|_| {});
+    let mut tokens = TokenStream::new();
+    quote2::quote!(tokens, { #body #body });
+}
+
+#[test]
+fn test_static_chunk() {
+    // Long runs without interpolations are written as one parsed string.
+    let name = format_ident!("Wrapper");
+    let mut tokens = TokenStream::new();
+    quote2::quote!(tokens, {
+        #[doc = "A wrapper."]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        pub struct #name<'a, T: ?Sized + 'static>(&'a T);
+        impl<'a, T: ?Sized + 'static> ::core::ops::Deref for #name<'a, T> {
+            type Target = T;
+            #[inline]
+            fn deref(&self) -> &T {
+                let r#type = r"raw";
+                let _ = (r#type, 1u8, 2.5f32, b'x', [0; 4], -1i64 >> 2, a::<u8>() != 0);
+                self.0
+            }
+        }
+    });
+    let expected: TokenStream = r#"
+        #[doc = "A wrapper."]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        pub struct Wrapper<'a, T: ?Sized + 'static>(&'a T);
+        impl<'a, T: ?Sized + 'static> ::core::ops::Deref for Wrapper<'a, T> {
+            type Target = T;
+            #[inline]
+            fn deref(&self) -> &T {
+                let r#type = r"raw";
+                let _ = (r#type, 1u8, 2.5f32, b'x', [0; 4], -1i64 >> 2, a::<u8>() != 0);
+                self.0
+            }
+        }
+    "#
+    .parse()
+    .unwrap();
+    quote2::testing::assert_tokens_eq!(tokens, expected);
+    // Spacing is kept too.
+    assert!(tokens.to_string().contains("(& 'a T) ;"));
+    assert!(tokens
+        .to_string()
+        .contains("- 1i64 >> 2 , a ::< u8 > () != 0"));
+
+    // Spanned like short templates, not with the location in the string.
+    fn assert_call_site(stream: TokenStream) {
+        let call_site = Span::call_site();
+        for tt in stream {
+            let span = tt.span();
+            assert_eq!(
+                (span.start(), span.end()),
+                (call_site.start(), call_site.end()),
+                "{tt}"
+            );
+            if let proc_macro2::TokenTree::Group(g) = tt {
+                assert_call_site(g.stream());
+            }
+        }
+    }
+    assert_call_site(tokens);
+}
+
+#[test]
+fn test_quote_cached() {
+    fn derive(name: &Ident, fields: &[Ident]) -> TokenStream {
+        let mut tokens = TokenStream::new();
+        quote2::quote_cached!(tokens, {
+            impl Clone for #name {
+                fn clone(&self) -> Self {
+                    Self { #(#fields: self.#fields.clone()),* }
+                }
+            }
+        });
+        tokens
+    }
+    fn spans(tokens: TokenStream, out: &mut Vec<String>) {
+        for tt in tokens {
+            out.push(format!("{} {:?}", tt, tt.span()));
+            if let proc_macro2::TokenTree::Group(g) = tt {
+                spans(g.stream(), out);
+            }
+        }
+    }
+    // A span with a location, unlike `Span::call_site()`.
+    let located: TokenStream = "  A".parse().unwrap();
+    let Some(proc_macro2::TokenTree::Ident(a)) = located.into_iter().next() else {
+        unreachable!()
+    };
+    assert_ne!(
+        format!("{:?}", a.span()),
+        format!("{:?}", Span::call_site())
+    );
+    let b = format_ident!("B");
+
+    for _ in 0..2 {
+        let tokens = derive(&a, &[format_ident!("x")]);
+        assert_eq!(
+            tokens.to_string(),
+            "impl Clone for A { fn clone (& self) -> Self { Self { x : self . x . clone () } } }"
+        );
+        let mut all = Vec::new();
+        spans(tokens, &mut all);
+        let call_site = format!("{:?}", Span::call_site());
+        for span in &all {
+            if span.starts_with("A ") {
+                assert_eq!(span, &format!("A {:?}", a.span()));
+            } else {
+                assert!(span.ends_with(&call_site), "{span}");
+            }
+        }
+    }
+    let tokens = derive(&b, &[format_ident!("y"), format_ident!("z")]);
+    assert_eq!(
+        tokens.to_string(),
+        "impl Clone for B { fn clone (& self) -> Self { Self { y : self . y . clone () , z : self . z . clone () } } }"
+    );
+}
+
+#[test]
+fn test_escape() {
+    let ty = format_ident!("Field");
+    let items = vec![format_ident!("a"), format_ident!("b")];
+    let mut tokens = TokenStream::new();
+    quote2::quote!(tokens, {
+        fn gen(tokens: &mut TokenStream, name: &Ident) {
+            quote2::quote!(tokens, {
+                #[derive(Debug)]
+                struct ##name(###ty);
+                ##(##items),*
+            });
+        }
+        const ITEMS: &[&str] = &[#(stringify!(#items)),*];
+    });
+    let expected = concat!(
+        "fn gen (tokens : & mut TokenStream , name : & Ident) { ",
+        "quote2 :: quote ! (tokens , { ",
+        "# [derive (Debug)] ",
+        "struct # name (#Field) ; ",
+        "# (# items) ,* ",
+        "}) ; ",
+        "} ",
+        "const ITEMS : & [& str] = & [stringify ! (a) , stringify ! (b)] ;"
+    );
+    assert_eq!(expected, tokens.to_string());
+}
+
+#[test]
+fn test_quote_each_token() {
+    let name = format_ident!("S");
+    let mut tokens = quote!(#[derive(Debug)]);
+    quote2::compat::quote_each_token!(tokens struct #name;);
+    assert_eq!("# [derive (Debug)] struct S ;", tokens.to_string());
+}
+
+#[test]
+fn test_compat_syntax() {
+    let x = format_ident!("x");
+    let name = format_ident!("name");
+    let span = Span::call_site();
+    let tokens = quote! {
+        ##x #{ a } match v { #name @ Some(_) => {} }
+    };
+    assert_eq!(
+        "# x # { a } match v { name @ Some (_) => { } }",
+        tokens.to_string()
+    );
+    let tokens = quote_spanned! {span=> ##x (a) @ span };
+    assert_eq!("# x (a) @ span", tokens.to_string());
+}
+
 #[test]
 fn test_array() {
     let array = QuoteIter([0u8; 40]);
@@ -184,6 +862,35 @@ fn test_floating() {
     assert_eq!(expected, tokens.to_string());
 }
 
+#[test]
+fn test_literal_template() {
+    let tokens = quote! {
+        1u8 2i64 3usize 4 0xff 0b1i8 1_000 0x1F_u8 007 256u8 128i8
+        1.5 2.5f32 1f64 1e3 0.10 1_000.5 1e3f32
+        'a' '\n' b'b' "s\n" "\x41" b"bytes" c"cstr"
+        r"raw" br"raw" 1u256
+    };
+    let expected = concat!(
+        "1u8 2i64 3usize 4 0xff 0b1i8 1_000 0x1F_u8 007 256u8 128i8 ",
+        "1.5 2.5f32 1f64 1e3 0.10 1_000.5 1e3f32 ",
+        "'a' '\\n' b'b' \"s\\n\" \"\\x41\" b\"bytes\" c\"cstr\" ",
+        "r\"raw\" br\"raw\" 1u256"
+    );
+    assert_eq!(expected, tokens.to_string());
+
+    let span = Span::call_site();
+    let spanned = quote_spanned! {span=>
+        1u8 2.5f32 'a' "s" r"raw"
+    };
+    assert_eq!("1u8 2.5f32 'a' \"s\" r\"raw\"", spanned.to_string());
+
+    // Parsed literals are spanned like the others.
+    let call_site = Span::call_site();
+    for tt in tokens {
+        assert_eq!(tt.span().end(), call_site.end(), "{tt}");
+    }
+}
+
 #[test]
 fn test_char() {
     let zero = '\u{1}';
@@ -417,6 +1124,47 @@ fn test_quote_raw_id() {
     assert_eq!(id.to_string(), "r#raw_id");
 }
 
+#[test]
+fn test_quote_spanned_raw_id() {
+    let span = Span::call_site();
+    let tokens = quote_spanned!(span=> let r#type = r#match;);
+    assert_eq!(tokens.to_string(), "let r#type = r#match ;");
+}
+
+#[test]
+fn test_ident_escaped() {
+    let cases = [
+        ("name", "name"),
+        ("type", "r#type"),
+        ("r#type", "r#type"),
+        ("r#name", "name"),
+        ("async", "r#async"),
+        ("union", "union"),
+        ("self", "self_"),
+        ("Self", "Self_"),
+        ("crate", "crate_"),
+        ("_", "_"),
+        ("", "_"),
+        ("content-type", "content_type"),
+        ("2d", "_2d"),
+        ("x.y z", "x_y_z"),
+        ("café", "café"),
+    ];
+    for (name, expected) in cases {
+        assert_eq!(
+            quote2::ident_escaped(name).to_string(),
+            expected,
+            "{name:?}"
+        );
+    }
+
+    let field = quote2::ident_escaped("type");
+    let mut tokens = TokenStream::new();
+    quote2::quote!(tokens, { self.#field });
+    let expected: TokenStream = "self.r#type".parse().unwrap();
+    quote2::testing::assert_tokens_eq!(tokens, expected);
+}
+
 #[test]
 fn test_type_inference_for_span() {
     trait CallSite {
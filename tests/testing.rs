@@ -0,0 +1,64 @@
+use proc_macro2::TokenStream;
+use quote2::testing::assert_tokens_eq;
+use quote2::{format_ident, quote};
+use std::panic;
+
+fn failure(f: impl FnOnce() + panic::UnwindSafe) -> String {
+    let err = panic::catch_unwind(f).unwrap_err();
+    match err.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(_) => panic!("expected a formatted panic message"),
+    }
+}
+
+#[test]
+fn test_assert_tokens_eq() {
+    let name = format_ident!("f");
+    let mut tokens = TokenStream::new();
+    quote!(tokens, {
+        fn #name() -> Vec<u8> { vec![1, 2] }
+    });
+    // `->` and `- >`, `: :` and `::` differ in spacing only.
+    let parsed: TokenStream = "fn f() - > Vec<u8> { vec![1, 2] }".parse().unwrap();
+    assert_tokens_eq!(tokens, parsed);
+    assert_tokens_eq!(tokens, {
+        fn #name() -> Vec<u8> {
+            vec![1, 2]
+        }
+    });
+}
+
+#[test]
+fn test_assert_tokens_eq_message() {
+    let msg = failure(|| {
+        let actual: TokenStream = "fn f() { let x = 1; if x { g(x, 2) } }".parse().unwrap();
+        assert_tokens_eq!(actual, {
+            fn f() {
+                let x = 1;
+                if x {
+                    g(x, 3)
+                }
+            }
+        });
+    });
+    assert_eq!(
+        msg,
+        "assertion `left == right` failed: token streams differ
+first difference at token 2 in root > { ... } (token 3) > { ... } (token 7) > ( ... ) (token 1)
+  left: `2`
+ right: `3`
+--- left
++++ right
+  fn f() {
+      let x = 1;
+      if x {
+-         g(x, 2)
++         g(x, 3)
+      }
+  }
+"
+    );
+
+    let msg = failure(|| assert_tokens_eq!(quote2::compat::quote!(a b), { a }));
+    assert!(msg.contains("first difference at token 1 in root\n  left: `b`\n right: end of group"));
+}
@@ -0,0 +1,9 @@
+use quote2::{proc_macro2::TokenStream, quote};
+
+fn main() {
+    let mut tokens = TokenStream::new();
+    let flag = true;
+    quote!(tokens, { #else { a } });
+    quote!(tokens, { #if flag });
+    quote!(tokens, { #match flag { true => a, false => { b } } });
+}
@@ -0,0 +1,8 @@
+use quote2::{proc_macro2::TokenStream, quote};
+
+fn main() {
+    let mut tokens = TokenStream::new();
+    let items = [1, 2, 3];
+    quote!(tokens, { #(#items),  });
+    quote!(tokens, { #(a),* });
+}
@@ -0,0 +1,7 @@
+use quote2::quote;
+
+fn main() {
+    quote!(, {
+        struct S;
+    });
+}
@@ -0,0 +1,8 @@
+use quote2::{proc_macro2::TokenStream, quote};
+
+fn main() {
+    let mut tokens = TokenStream::new();
+    quote!(tokens {
+        struct S;
+    });
+}
@@ -0,0 +1,6 @@
+use quote2::{proc_macro2::TokenStream, unquote};
+
+fn main() {
+    let input = TokenStream::new();
+    let _ = unquote!(input, { #(#items),* });
+}
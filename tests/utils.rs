@@ -0,0 +1,77 @@
+use proc_macro2::TokenStream;
+use quote2::utils::{
+    quote_join, quote_join_trailing, quote_rep_once, quote_sep, quote_sep_enumerate,
+    quote_sep_trailing, Sep,
+};
+use quote2::{format_ident, quote, Quote};
+
+fn render(t: impl quote2::ToTokens) -> String {
+    t.into_token_stream().to_string()
+}
+
+#[test]
+fn test_quote_sep() {
+    let write = |t: &mut TokenStream, n: u8| {
+        quote!(t, { #n });
+    };
+    assert_eq!(render(quote_sep(1..4, ',', write)), "1u8 , 2u8 , 3u8");
+    assert_eq!(render(quote_sep(1..1, ',', write)), "");
+    assert_eq!(render(quote_sep_trailing(1..3, ';', write)), "1u8 ; 2u8 ;");
+    assert_eq!(render(quote_sep_trailing(1..1, ';', write)), "");
+    assert_eq!(render(quote_sep(1..3, "=>", write)), "1u8 => 2u8");
+    let and = Sep(format_ident!("and"));
+    assert_eq!(render(quote_sep(1..3, and, write)), "1u8 and 2u8");
+}
+
+#[test]
+#[should_panic = "separator \"and\" contains 'a', which is not a punctuation char"]
+fn test_quote_sep_invalid() {
+    render(quote_sep(1..3, "and", |t, n: u8| quote!(t, { #n })));
+}
+
+#[test]
+fn test_quote_sep_enumerate() {
+    let names = ["a", "b"];
+    let fields = quote_sep_enumerate(names, ',', |t, i, name| {
+        let name = format_ident!("{name}");
+        quote!(t, { #name: #i });
+    });
+    assert_eq!(render(&fields), "a : 0usize , b : 1usize");
+    // `QuoteFn` can be interpolated as often as needed.
+    let mut tokens = TokenStream::new();
+    quote!(tokens, { [#fields] [#fields] });
+    assert_eq!(
+        tokens.to_string(),
+        "[a : 0usize , b : 1usize] [a : 0usize , b : 1usize]"
+    );
+}
+
+#[test]
+fn test_quote_join() {
+    let tys = ["u8", "u16"].map(|ty| format_ident!("{ty}"));
+    assert_eq!(render(quote_join(&tys, '|')), "u8 | u16");
+    assert_eq!(render(quote_join_trailing(&tys, ',')), "u8 , u16 ,");
+    let empty: [proc_macro2::Ident; 0] = [];
+    assert_eq!(render(quote_join_trailing(empty, ',')), "");
+}
+
+#[test]
+fn test_quote_rep_once() {
+    let mut names = vec![format_ident!("a"), format_ident!("b")];
+    let mut count = 0;
+    let lets = quote_rep_once(names.drain(..), |t, name| {
+        count += 1;
+        quote!(t, { let #name = #count; });
+    });
+    assert_eq!(render(lets), "let a = 1i32 ; let b = 2i32 ;");
+    assert!(names.is_empty());
+}
+
+#[test]
+fn test_quote_mut() {
+    let mut iter = ["a", "b"].into_iter().map(|name| format_ident!("{name}"));
+    let next = quote2::quote_mut(move |t| t.add_tokens(iter.next()));
+    let mut tokens = TokenStream::new();
+    quote!(tokens, { #next + #next + #next });
+    assert_eq!(tokens.to_string(), "a + b +");
+}